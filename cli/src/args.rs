@@ -0,0 +1,95 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Options that are followed by a value.
+const VALUE_OPTIONS: &[&str] = &["schema", "type", "output", "format"];
+
+/// Command-line arguments. Commands take the options they understand, and then call [`Args::finish`] to reject the rest.
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: VecDeque<String>,
+    flags: Vec<String>,
+    values: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(args: Vec<String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push_back(arg);
+                continue;
+            };
+            if let Some((name, value)) = name.split_once('=') {
+                parsed.values.insert(name.to_string(), value.to_string());
+            } else if VALUE_OPTIONS.contains(&name) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("--{name} needs a value"))?;
+                parsed.values.insert(name.to_string(), value);
+            } else {
+                parsed.flags.push(name.to_string());
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn next_positional(&mut self) -> Option<String> {
+        self.positional.pop_front()
+    }
+
+    /// Takes the next positional argument, failing if there isn't one.
+    pub fn positional(&mut self, name: &str) -> Result<String, String> {
+        self.next_positional()
+            .ok_or_else(|| format!("missing {name}, see `sbof help`"))
+    }
+
+    /// Takes `--name`, returning whether it was given.
+    pub fn flag(&mut self, name: &str) -> bool {
+        let len = self.flags.len();
+        self.flags.retain(|flag| flag != name);
+        self.flags.len() < len
+    }
+
+    /// Takes `--name <value>`.
+    pub fn value(&mut self, name: &str) -> Option<String> {
+        self.values.remove(name)
+    }
+
+    /// Fails if any arguments weren't taken.
+    pub fn finish(self) -> Result<(), String> {
+        if let Some(arg) = self.positional.front() {
+            return Err(format!("unexpected argument `{arg}`"));
+        }
+        let unknown = self.flags.first().or(self.values.keys().next());
+        match unknown {
+            Some(name) => Err(format!("unknown option `--{name}`")),
+            None => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn args_test() {
+    let args = [
+        "inspect",
+        "save.bin",
+        "--type=Player",
+        "--schema",
+        "game.sbof",
+        "--no-header",
+    ];
+    let mut args = Args::parse(args.map(String::from).to_vec()).unwrap();
+    assert_eq!(args.next_positional().as_deref(), Some("inspect"));
+    assert_eq!(args.positional("file").unwrap(), "save.bin");
+    assert_eq!(args.value("schema").as_deref(), Some("game.sbof"));
+    assert_eq!(args.value("type").as_deref(), Some("Player"));
+    assert!(args.flag("no-header"));
+    assert!(!args.flag("no-header"));
+    assert!(args.finish().is_ok());
+
+    let mut args = Args::parse(vec!["--bogus".into(), "x".into()]).unwrap();
+    assert_eq!(args.positional("file").unwrap(), "x");
+    assert_eq!(args.finish().unwrap_err(), "unknown option `--bogus`");
+    assert!(Args::parse(vec!["--schema".into()]).is_err());
+}
@@ -0,0 +1,590 @@
+//! `sbof convert`: converts SBOF data to JSON, RON or TOML and back, with a schema.
+//!
+//! The document holds the header, if the data has one, and the value:
+//!
+//! ```json
+//! { "header": { "version": 0, "flags": 1 }, "value": { "name": "house", "tags": [1, 2] } }
+//! ```
+//!
+//! Values are stored the way `serde_json` would store the Rust types, with a few changes so that nothing is lost when converting back:
+//! - 128-bit integers that don't fit in 64 bits are strings.
+//! - Floats that JSON can't store are the strings `"NaN"`, `"inf"` and `"-inf"`.
+//! - Byte arrays are hex strings.
+//! - Maps are arrays of `[key, value]` pairs, unless their keys are strings.
+//! - `Some` is an array holding the value if the value is an option, `()` or a struct without fields, since `null` would be ambiguous.
+//!
+//! RON documents are the same as JSON ones. TOML doesn't have `null`, so it's an empty table instead, integers that don't fit in 64-bit signed integers are strings, and maps are always arrays of pairs, since TOML moves tables after the other keys.
+
+use std::fs;
+
+use ron::ser::PrettyConfig;
+use sbof::{
+    FeatureFlags, Header, SbofOptions, Value,
+    schema::{Schema, VariantKind},
+    value::VariantForm,
+};
+use serde_json::{Map, Number, Value as Json, json};
+
+use crate::args::Args;
+
+pub fn run(mut args: Args) -> Result<(), String> {
+    let path = args.positional("file")?;
+    let output = args.value("output");
+    let format = args.value("format");
+    let options = crate::options(&mut args);
+    let schema = crate::schema(&mut args)?.ok_or("converting needs --schema")?;
+    args.finish()?;
+
+    let converted = if let Some(input) = Format::of_path(&path) {
+        let src = fs::read_to_string(&path).map_err(|e| format!("couldn't read `{path}`: {e}"))?;
+        let json = input.parse(&src).map_err(|e| format!("in `{path}`: {e}"))?;
+        from_document(&json, &schema, &options, input)?
+    } else {
+        let format = match format {
+            Some(name) => Format::from_name(&name)
+                .ok_or_else(|| format!("unknown format `{name}`, expected json, ron or toml"))?,
+            None => output
+                .as_deref()
+                .and_then(Format::of_path)
+                .unwrap_or(Format::Json),
+        };
+        let json = to_document(&crate::read(&path)?, &schema, &options, format)?;
+        format.print(&json)?.into_bytes()
+    };
+
+    match output {
+        Some(output) => {
+            fs::write(&output, converted).map_err(|e| format!("couldn't write `{output}`: {e}"))
+        }
+        None => {
+            use std::io::Write;
+            std::io::stdout()
+                .write_all(&converted)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// The text formats that SBOF data can be converted to. Documents are built as JSON values, and then written in the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Ron,
+    Toml,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "ron" => Some(Format::Ron),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    /// The format of a file, by its extension.
+    fn of_path(path: &str) -> Option<Format> {
+        Format::from_name(path.rsplit_once('.')?.1)
+    }
+
+    /// How `()` and `None` are written. TOML doesn't have `null`, so they're an empty table there.
+    fn null(self) -> Json {
+        match self {
+            Format::Toml => Json::Object(Map::new()),
+            _ => Json::Null,
+        }
+    }
+
+    fn parse(self, src: &str) -> Result<Json, String> {
+        match self {
+            Format::Json => serde_json::from_str(src).map_err(|e| e.to_string()),
+            Format::Ron => ron::from_str(src).map_err(|e| e.to_string()),
+            Format::Toml => toml::from_str(src).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn print(self, json: &Json) -> Result<String, String> {
+        let mut text = match self {
+            Format::Json => serde_json::to_string_pretty(json).map_err(|e| e.to_string())?,
+            Format::Ron => ron::ser::to_string_pretty(json, PrettyConfig::default())
+                .map_err(|e| e.to_string())?,
+            Format::Toml => toml::to_string_pretty(json).map_err(|e| e.to_string())?,
+        };
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Ok(text)
+    }
+}
+
+/// Converts SBOF data to a document.
+fn to_document(
+    bytes: &[u8],
+    schema: &Schema,
+    options: &SbofOptions,
+    format: Format,
+) -> Result<Json, String> {
+    let value = Value::from_bytes_options(bytes, schema, options).map_err(|e| e.to_string())?;
+    let mut document = Map::new();
+    if options.header() {
+        let header = Header::read(bytes).map_err(|e| e.to_string())?;
+        document.insert(
+            "header".into(),
+            json!({ "version": header.version, "flags": header.flags.bits() }),
+        );
+    }
+    document.insert("value".into(), to_json(&value, format));
+    Ok(Json::Object(document))
+}
+
+/// Converts a document back to SBOF data, with the header it holds.
+fn from_document(
+    json: &Json,
+    schema: &Schema,
+    options: &SbofOptions,
+    format: Format,
+) -> Result<Vec<u8>, String> {
+    let value = json.get("value").ok_or("the document has no `value`")?;
+    let value = from_json(value, schema, "", format)?;
+    let options = match json.get("header") {
+        Some(header) => {
+            let field = |name| {
+                header
+                    .get(name)
+                    .and_then(Json::as_u64)
+                    .and_then(|n| u8::try_from(n).ok())
+                    .ok_or(format!("the header's `{name}` isn't a byte"))
+            };
+            let flags = FeatureFlags::from_bits(field("flags")?)
+                .ok_or("the header sets unknown feature flags")?;
+            options
+                .with_header(true)
+                .with_version(field("version")?)
+                .with_feature_flags(flags)
+        }
+        None => options.with_header(false),
+    };
+    value.to_bytes_options(&options).map_err(|e| e.to_string())
+}
+
+fn to_json(value: &Value, format: Format) -> Json {
+    let to_json = |value| to_json(value, format);
+    match value {
+        Value::Bool(v) => json!(v),
+        Value::U8(v) => json!(v),
+        Value::U16(v) => json!(v),
+        Value::U32(v) => json!(v),
+        Value::U64(v) => uint_to_json(*v as u128, format),
+        Value::U128(v) => uint_to_json(*v, format),
+        Value::I8(v) => json!(v),
+        Value::I16(v) => json!(v),
+        Value::I32(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::I128(v) => i64::try_from(*v).map_or_else(|_| json!(v.to_string()), |v| json!(v)),
+        // The shortest decimal that reads back as the same f32
+        Value::F32(v) => float_to_json(v.to_string().parse().unwrap_or(f64::NAN), *v as f64),
+        Value::F64(v) => float_to_json(*v, *v),
+        Value::Char(v) => json!(v),
+        Value::Str(v) => json!(v),
+        Value::Bytes(v) => json!(v.iter().map(|b| format!("{b:02x}")).collect::<String>()),
+        Value::Unit => format.null(),
+        Value::Option(None) => format.null(),
+        Value::Option(Some(v)) => match &**v {
+            Value::Option(_) | Value::Unit => json!([to_json(v)]),
+            Value::Struct(fields) if fields.is_empty() => json!([to_json(v)]),
+            _ => to_json(v),
+        },
+        Value::Seq(values) | Value::Tuple(values) => values.iter().map(to_json).collect(),
+        Value::Map(entries)
+            if format != Format::Toml
+                && entries.iter().all(|(k, _)| matches!(k, Value::Str(_))) =>
+        {
+            Json::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| match k {
+                        Value::Str(k) => (k.clone(), to_json(v)),
+                        _ => unreachable!("every key is a string"),
+                    })
+                    .collect(),
+            )
+        }
+        Value::Map(entries) => entries
+            .iter()
+            .map(|(k, v)| json!([to_json(k), to_json(v)]))
+            .collect(),
+        Value::Struct(fields) => Json::Object(
+            fields
+                .iter()
+                .map(|(name, v)| (name.clone(), to_json(v)))
+                .collect(),
+        ),
+        Value::Enum {
+            variant,
+            form,
+            value,
+            ..
+        } => match form {
+            VariantForm::Unit => json!(variant),
+            _ => json!({ variant.clone(): to_json(value) }),
+        },
+    }
+}
+
+/// Unsigned integers are numbers if the format can store them, and strings otherwise.
+fn uint_to_json(v: u128, format: Format) -> Json {
+    let fits = match format {
+        Format::Toml => i64::try_from(v).is_ok(),
+        _ => u64::try_from(v).is_ok(),
+    };
+    match fits {
+        true => json!(v as u64),
+        false => json!(v.to_string()),
+    }
+}
+
+/// `finite` is written as a number, and `v` decides which string is written if it isn't finite.
+fn float_to_json(finite: f64, v: f64) -> Json {
+    match Number::from_f64(finite) {
+        Some(n) => Json::Number(n),
+        None if v.is_nan() => json!("NaN"),
+        None if v > 0.0 => json!("inf"),
+        None => json!("-inf"),
+    }
+}
+
+fn from_json(json: &Json, schema: &Schema, path: &str, format: Format) -> Result<Value, String> {
+    let from_json = |json, schema, path: &str| from_json(json, schema, path, format);
+    let null = format.null();
+    let expected = |what: &str| {
+        let at = if path.is_empty() {
+            "the top level"
+        } else {
+            path
+        };
+        format!("expected {what} at `{at}`, found {json}")
+    };
+    let uint = || match json {
+        Json::Number(n) => n.as_u64().map(u128::from),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    let int = || match json {
+        Json::Number(n) => n.as_i64().map(i128::from),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    let float = || match json {
+        Json::Number(n) => n.as_f64(),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    let array = |len: Option<usize>| match json {
+        Json::Array(values) if len.is_none_or(|len| values.len() == len) => Ok(values),
+        _ => Err(expected(&match len {
+            Some(len) => format!("an array of {len}"),
+            None => "an array".into(),
+        })),
+    };
+    macro_rules! narrow {
+        ($read:expr, $variant:ident, $name:literal) => {
+            $read
+                .and_then(|v| v.try_into().ok())
+                .map(Value::$variant)
+                .ok_or_else(|| expected($name))
+        };
+    }
+
+    match schema {
+        Schema::Bool => json
+            .as_bool()
+            .map(Value::Bool)
+            .ok_or_else(|| expected("a bool")),
+        Schema::U8 => narrow!(uint(), U8, "a u8"),
+        Schema::U16 => narrow!(uint(), U16, "a u16"),
+        Schema::U32 => narrow!(uint(), U32, "a u32"),
+        Schema::U64 => narrow!(uint(), U64, "a u64"),
+        Schema::U128 => narrow!(uint(), U128, "a u128"),
+        Schema::I8 => narrow!(int(), I8, "an i8"),
+        Schema::I16 => narrow!(int(), I16, "an i16"),
+        Schema::I32 => narrow!(int(), I32, "an i32"),
+        Schema::I64 => narrow!(int(), I64, "an i64"),
+        Schema::I128 => narrow!(int(), I128, "an i128"),
+        Schema::F32 => float()
+            .map(|v| Value::F32(v as f32))
+            .ok_or_else(|| expected("a float")),
+        Schema::F64 => float().map(Value::F64).ok_or_else(|| expected("a float")),
+        Schema::Char => {
+            let mut chars = json.as_str().map(str::chars).into_iter().flatten();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Value::Char(c)),
+                _ => Err(expected("a single character")),
+            }
+        }
+        Schema::Str => json
+            .as_str()
+            .map(|s| Value::Str(s.into()))
+            .ok_or_else(|| expected("a string")),
+        Schema::Bytes => json
+            .as_str()
+            .and_then(parse_hex)
+            .map(Value::Bytes)
+            .ok_or_else(|| expected("a hex string")),
+        Schema::Unit if *json == null => Ok(Value::Unit),
+        Schema::Unit => Err(expected(&null.to_string())),
+        Schema::Option(inner) => match (json, &**inner) {
+            (json, _) if *json == null => Ok(Value::Option(None)),
+            (json, inner) if wrapped(inner) => match json {
+                Json::Array(values) if values.len() == 1 => {
+                    let value = from_json(&values[0], inner, path)?;
+                    Ok(Value::Option(Some(Box::new(value))))
+                }
+                _ => Err(expected(&format!("{null}, or an array holding the value"))),
+            },
+            (json, inner) => Ok(Value::Option(Some(Box::new(from_json(json, inner, path)?)))),
+        },
+        Schema::Seq(element) => array(None)?
+            .iter()
+            .enumerate()
+            .map(|(i, v)| from_json(v, element, &format!("{path}[{i}]")))
+            .collect::<Result<_, _>>()
+            .map(Value::Seq),
+        Schema::Tuple(elements) => tuple(array(Some(elements.len()))?, elements, path, format),
+        Schema::Map { key, value } => {
+            let entries = match (json, &**key) {
+                (Json::Object(map), Schema::Str) => map
+                    .iter()
+                    .map(|(k, v)| {
+                        let v = from_json(v, value, &format!("{path}{{{k:?}}}"))?;
+                        Ok((Value::Str(k.clone()), v))
+                    })
+                    .collect::<Result<_, String>>()?,
+                _ => array(None)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        let path = format!("{path}{{{i}}}");
+                        match entry {
+                            Json::Array(pair) if pair.len() == 2 => Ok((
+                                from_json(&pair[0], key, &path)?,
+                                from_json(&pair[1], value, &path)?,
+                            )),
+                            _ => Err(format!("expected a [key, value] pair at `{path}`")),
+                        }
+                    })
+                    .collect::<Result<_, String>>()?,
+            };
+            Ok(Value::Map(entries))
+        }
+        Schema::Struct { fields, .. } => {
+            struct_fields(json, fields, path, format).map(Value::Struct)
+        }
+        Schema::Enum { variants, .. } => {
+            let (name, data) = match json {
+                Json::String(name) => (name, &null),
+                Json::Object(map) if map.len() == 1 => map.iter().next().expect("one entry"),
+                _ => return Err(expected("a variant name, or an object with one variant")),
+            };
+            let (index, variant) = variants
+                .iter()
+                .enumerate()
+                .find(|(_, v)| &v.name == name)
+                .ok_or_else(|| format!("unknown variant `{name}` at `{path}`"))?;
+            let path = format!("{path}.{name}");
+            let value = match &variant.kind {
+                VariantKind::Unit => Value::Unit,
+                VariantKind::Newtype(inner) => from_json(data, inner, &path)?,
+                VariantKind::Tuple(elements) => match data {
+                    Json::Array(values) if values.len() == elements.len() => {
+                        tuple(values, elements, &path, format)?
+                    }
+                    _ => {
+                        return Err(format!(
+                            "expected an array of {} at `{path}`",
+                            elements.len()
+                        ));
+                    }
+                },
+                VariantKind::Struct(fields) => {
+                    Value::Struct(struct_fields(data, fields, &path, format)?)
+                }
+            };
+            Ok(Value::Enum {
+                index: index as u32,
+                variant: name.clone(),
+                form: VariantForm::from(&variant.kind),
+                value: Box::new(value),
+            })
+        }
+    }
+}
+
+/// Whether `Some` holds its value in an array with this layout, since the value could be mistaken for `None` otherwise.
+fn wrapped(schema: &Schema) -> bool {
+    match schema {
+        Schema::Option(_) | Schema::Unit => true,
+        Schema::Struct { fields, .. } => fields.is_empty(),
+        _ => false,
+    }
+}
+
+fn tuple(values: &[Json], schemas: &[Schema], path: &str, format: Format) -> Result<Value, String> {
+    values
+        .iter()
+        .zip(schemas)
+        .enumerate()
+        .map(|(i, (v, schema))| from_json(v, schema, &format!("{path}.{i}"), format))
+        .collect::<Result<_, _>>()
+        .map(Value::Tuple)
+}
+
+fn struct_fields(
+    json: &Json,
+    fields: &[sbof::schema::Field],
+    path: &str,
+    format: Format,
+) -> Result<Vec<(String, Value)>, String> {
+    let Json::Object(map) = json else {
+        return Err(format!("expected an object at `{path}`, found {json}"));
+    };
+    if let Some(unknown) = map.keys().find(|k| !fields.iter().any(|f| &f.name == *k)) {
+        return Err(format!("unknown field `{unknown}` at `{path}`"));
+    }
+    fields
+        .iter()
+        .map(|field| {
+            let path = format!("{path}.{}", field.name);
+            let value = map
+                .get(&field.name)
+                .ok_or_else(|| format!("missing field at `{path}`"))?;
+            Ok((
+                field.name.clone(),
+                from_json(value, &field.schema, &path, format)?,
+            ))
+        })
+        .collect()
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    let digits: Vec<_> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+        .collect()
+}
+
+#[test]
+fn convert_test() -> Result<(), String> {
+    let idl = sbof::idl::Idl::parse(
+        "
+        struct Save {
+            name: str,
+            big: u128,
+            ratio: f32,
+            nan: f64,
+            blob: bytes,
+            maybe: option<option<u8>>,
+            lookup: map<u16, char>,
+            named: map<str, bool>,
+            shapes: seq<Shape>,
+        }
+        enum Shape { Point, Circle(f32), Rect { w: u16, h: u16 } }
+        ",
+    )
+    .map_err(|e| e.to_string())?;
+    let schema = idl.schema("Save").unwrap();
+
+    let value = Value::Struct(vec![
+        ("name".into(), Value::Str("house".into())),
+        ("big".into(), Value::U128(u128::MAX)),
+        ("ratio".into(), Value::F32(0.1)),
+        ("nan".into(), Value::F64(f64::NEG_INFINITY)),
+        ("blob".into(), Value::Bytes(vec![0xde, 0xad])),
+        (
+            "maybe".into(),
+            Value::Option(Some(Box::new(Value::Option(None)))),
+        ),
+        (
+            "lookup".into(),
+            Value::Map(vec![(Value::U16(7), Value::Char('x'))]),
+        ),
+        (
+            "named".into(),
+            Value::Map(vec![
+                (Value::Str("z".into()), Value::Bool(true)),
+                (Value::Str("a".into()), Value::Bool(false)),
+            ]),
+        ),
+        (
+            "shapes".into(),
+            Value::Seq(vec![
+                Value::Enum {
+                    index: 0,
+                    variant: "Point".into(),
+                    form: VariantForm::Unit,
+                    value: Box::new(Value::Unit),
+                },
+                Value::Enum {
+                    index: 2,
+                    variant: "Rect".into(),
+                    form: VariantForm::Struct,
+                    value: Box::new(Value::Struct(vec![
+                        ("w".into(), Value::U16(300)),
+                        ("h".into(), Value::U16(1)),
+                    ])),
+                },
+            ]),
+        ),
+    ]);
+
+    let options = SbofOptions::new().with_high_precision(true);
+    let bytes = value
+        .to_bytes_options(&options)
+        .map_err(|e| e.to_string())?;
+    let json = to_document(&bytes, &schema, &SbofOptions::new(), Format::Json)?;
+    assert_eq!(json["header"], json!({ "version": 0, "flags": 1 }));
+    assert_eq!(json["value"]["big"], json!(u128::MAX.to_string()));
+    assert_eq!(json["value"]["ratio"], json!(0.1));
+    assert_eq!(json["value"]["nan"], json!("-inf"));
+    assert_eq!(json["value"]["blob"], json!("dead"));
+    assert_eq!(json["value"]["maybe"], json!([null]));
+    assert_eq!(json["value"]["lookup"], json!([[7, "x"]]));
+    assert_eq!(
+        serde_json::to_string(&json["value"]["named"]).unwrap(),
+        r#"{"z":true,"a":false}"#
+    );
+    assert_eq!(
+        json["value"]["shapes"],
+        json!(["Point", { "Rect": { "w": 300, "h": 1 } }])
+    );
+
+    // Converting back gives the same bytes, in every format
+    for format in [Format::Json, Format::Ron, Format::Toml] {
+        let json = to_document(&bytes, &schema, &SbofOptions::new(), format)?;
+        let json = format.parse(&format.print(&json)?)?;
+        assert_eq!(
+            from_document(&json, &schema, &SbofOptions::new(), format)?,
+            bytes,
+            "{format:?}"
+        );
+    }
+
+    // TOML has no null, and moves tables after the other keys, so maps with string keys stay in order as pairs
+    let toml = to_document(&bytes, &schema, &SbofOptions::new(), Format::Toml)?;
+    assert_eq!(toml["value"]["big"], json!(u128::MAX.to_string()));
+    assert_eq!(toml["value"]["maybe"], json!([{}]));
+    assert_eq!(toml["value"]["named"], json!([["z", true], ["a", false]]));
+
+    let mut json = json;
+    json["value"]["shapes"][1]["Rect"]["w"] = json!(70000);
+    assert_eq!(
+        from_document(&json, &schema, &SbofOptions::new(), Format::Json).unwrap_err(),
+        "expected a u16 at `.shapes[1].Rect.w`, found 70000"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! `sbof inspect`: prints the header, and with a schema, an annotated hex dump.
+
+use std::fmt::Write;
+
+use sbof::{
+    Header,
+    inspect::{Encoding, Span, SpanKind},
+};
+
+use crate::args::Args;
+
+/// Bytes shown on each line of a dump, before the rest are left out.
+const MAX_BYTES: usize = 8;
+
+pub fn run(mut args: Args) -> Result<(), String> {
+    let path = args.positional("file")?;
+    let options = crate::options(&mut args);
+    let schema = crate::schema(&mut args)?;
+    args.finish()?;
+
+    let bytes = crate::read(&path)?;
+    let Some(schema) = schema else {
+        let data = if options.header() {
+            let header = Header::read(bytes.as_slice()).map_err(|e| e.to_string())?;
+            println!("{}", header_line(&header));
+            &bytes[Header::LEN..]
+        } else {
+            &bytes[..]
+        };
+        print!("{}", hex_dump(data, bytes.len() - data.len()));
+        return Ok(());
+    };
+
+    let inspection =
+        sbof::inspect::inspect_options(&bytes, &schema, &options).map_err(|e| e.to_string())?;
+    if let Some(header) = &inspection.header {
+        println!("{}", header_line(header));
+    }
+    print!("{}", annotate(&bytes, &inspection.spans));
+    Ok(())
+}
+
+fn header_line(header: &Header) -> String {
+    format!(
+        "header: version {}, feature flags: {}",
+        header.version, header.flags
+    )
+}
+
+/// A plain hex dump, with 16 bytes per line. `offset` is where `data` starts in the file.
+fn hex_dump(data: &[u8], offset: usize) -> String {
+    let mut out = String::new();
+    for (i, line) in data.chunks(16).enumerate() {
+        let hex: Vec<_> = line.iter().map(|b| format!("{b:02x}")).collect();
+        writeln!(out, "{:08x}  {}", offset + i * 16, hex.join(" ")).unwrap();
+    }
+    out
+}
+
+/// One line per span, with its bytes, path, what it is, and its decoded value.
+fn annotate(bytes: &[u8], spans: &[Span]) -> String {
+    let rows: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            let data = &bytes[span.offset..span.offset + span.len];
+            let mut hex: Vec<_> = data
+                .iter()
+                .take(MAX_BYTES)
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            if data.len() > MAX_BYTES {
+                hex.push(format!("(+{})", data.len() - MAX_BYTES));
+            }
+            let path = if span.path.is_empty() {
+                "."
+            } else {
+                &span.path
+            };
+            let what = format!("{}, {}", kind_name(span.kind), encoding_name(span.encoding));
+            (span.offset, hex.join(" "), path, what, &span.value)
+        })
+        .collect();
+
+    let hex_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
+    let path_width = rows.iter().map(|r| r.2.len()).max().unwrap_or(0);
+    let what_width = rows.iter().map(|r| r.3.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (offset, hex, path, what, value) in rows {
+        writeln!(
+            out,
+            "{offset:08x}  {hex:hex_width$}  {path:path_width$}  {what:what_width$}  {value}"
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn kind_name(kind: SpanKind) -> &'static str {
+    match kind {
+        SpanKind::Bool => "bool",
+        SpanKind::Int => "int",
+        SpanKind::Float => "float",
+        SpanKind::Char => "char",
+        SpanKind::Str => "str",
+        SpanKind::Bytes => "bytes",
+        SpanKind::Length => "length",
+        SpanKind::OptionMarker => "option marker",
+        SpanKind::VariantIndex => "variant",
+    }
+}
+
+fn encoding_name(encoding: Encoding) -> &'static str {
+    match encoding {
+        Encoding::Byte => "byte",
+        Encoding::LiteralVarint => "literal varint",
+        Encoding::PrefixedVarint => "prefixed varint",
+        Encoding::TransformedFloat => "transformed float",
+        Encoding::IeeeFloat => "IEEE 754 float",
+        Encoding::Raw => "raw",
+    }
+}
+
+#[test]
+fn annotate_test() {
+    let bytes = [0x00, 0x00, 0x01, 0x02, 0x68, 0x69, 0x05];
+    let span = |offset, len, path: &str, kind, encoding, value: &str| Span {
+        offset,
+        len,
+        path: path.into(),
+        kind,
+        encoding,
+        value: value.into(),
+    };
+    let spans = [
+        span(
+            2,
+            2,
+            ".name",
+            SpanKind::Length,
+            Encoding::PrefixedVarint,
+            "2",
+        ),
+        span(4, 2, ".name", SpanKind::Str, Encoding::Raw, "\"hi\""),
+        span(6, 1, ".level", SpanKind::Int, Encoding::LiteralVarint, "5"),
+    ];
+    assert_eq!(
+        annotate(&bytes, &spans),
+        "\
+00000002  01 02  .name   length, prefixed varint  2
+00000004  68 69  .name   str, raw                 \"hi\"
+00000006  05     .level  int, literal varint      5
+"
+    );
+    assert_eq!(hex_dump(&bytes[2..], 2), "00000002  01 02 68 69 05\n");
+}
@@ -0,0 +1,96 @@
+//! The `sbof` command-line tool, for debugging SBOF data by hand.
+
+mod args;
+mod convert;
+mod inspect;
+mod stats;
+
+use std::{env, fs, process::ExitCode};
+
+use args::Args;
+use sbof::{SbofOptions, idl::Idl, schema::Schema};
+
+const USAGE: &str = "\
+usage: sbof <command> [options]
+
+commands:
+  inspect <file>    print the header, and with a schema, an annotated hex dump
+  stats <file>      report how many bytes each part of the data takes, with a schema
+  convert <file>    convert SBOF data to JSON, RON or TOML, or a .json, .ron or .toml file back to SBOF, with a schema
+
+options:
+  --schema <file>   a .sbof schema file describing the data
+  --type <name>     the type in the schema file that the data holds, if it defines more than one
+  --output <file>   where to write converted data, instead of stdout
+  --format <name>   the format to convert SBOF data to: json, ron or toml (by default, from --output's extension, or json)
+  --no-header       the data doesn't start with a header
+  --high-precision  without a header, the data has the \"High Precision Floats\" feature flag
+  --typed           without a header, the data has the \"Typed\" feature flag
+  --skippable       without a header, the data has the \"Skippable\" feature flag
+  --named-fields    without a header, the data has the \"Named Fields\" feature flag
+";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut args = Args::parse(args)?;
+    let Some(command) = args.next_positional() else {
+        print!("{USAGE}");
+        return Ok(());
+    };
+    match command.as_str() {
+        "inspect" => inspect::run(args),
+        "stats" => stats::run(args),
+        "convert" => convert::run(args),
+        "help" | "--help" | "-h" => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(format!("unknown command `{command}`, see `sbof help`")),
+    }
+}
+
+/// The options for reading the data, from the command-line flags.
+fn options(args: &mut Args) -> SbofOptions {
+    SbofOptions::new()
+        .with_header(!args.flag("no-header"))
+        .with_high_precision(args.flag("high-precision"))
+        .with_typed(args.flag("typed"))
+        .with_skippable(args.flag("skippable"))
+        .with_named_fields(args.flag("named-fields"))
+}
+
+/// Loads the type called `--type` from the `--schema` file, if there is one.
+fn schema(args: &mut Args) -> Result<Option<Schema>, String> {
+    let Some(path) = args.value("schema") else {
+        return Ok(None);
+    };
+    let src = fs::read_to_string(&path).map_err(|e| format!("couldn't read `{path}`: {e}"))?;
+    let idl = Idl::parse(&src).map_err(|e| format!("in `{path}`: {e}"))?;
+    let name = match args.value("type") {
+        Some(name) => name,
+        None => match idl.types.as_slice() {
+            [def] => def.name.clone(),
+            _ => {
+                return Err(format!(
+                    "`{path}` defines more than one type, so --type is needed"
+                ));
+            }
+        },
+    };
+    idl.schema(&name)
+        .map(Some)
+        .ok_or_else(|| format!("`{path}` doesn't define a type called `{name}`"))
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("couldn't read `{path}`: {e}"))
+}
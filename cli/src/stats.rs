@@ -0,0 +1,333 @@
+//! `sbof stats`: reports where the bytes of SBOF data go, with a schema.
+
+use std::fmt::Write;
+
+use sbof::{
+    Header,
+    inspect::{Encoding, Inspection, SpanKind},
+    schema::{Schema, VariantKind},
+};
+
+use crate::args::Args;
+
+pub fn run(mut args: Args) -> Result<(), String> {
+    let path = args.positional("file")?;
+    let options = crate::options(&mut args);
+    let schema = crate::schema(&mut args)?.ok_or("stats need --schema")?;
+    args.finish()?;
+
+    let bytes = crate::read(&path)?;
+    let inspection =
+        sbof::inspect::inspect_options(&bytes, &schema, &options).map_err(|e| e.to_string())?;
+    print!("{}", report(&inspection, &schema));
+    Ok(())
+}
+
+/// Bytes taken by one path, with the elements of sequences and maps counted together.
+#[derive(Default)]
+struct PathBytes {
+    path: String,
+    /// Bytes of the value at the path itself, like a length prefix or the contents of a string
+    own: usize,
+    /// Bytes of the value at the path and everything inside it
+    total: usize,
+}
+
+#[derive(Default)]
+struct Varints {
+    /// `u8`s and `i8`s, which aren't varints
+    byte: usize,
+    literal: usize,
+    prefixed: usize,
+}
+
+#[derive(Default)]
+struct Floats {
+    count: usize,
+    bytes: usize,
+    /// What the floats would take as IEEE 754
+    ieee: usize,
+}
+
+fn report(inspection: &Inspection, schema: &Schema) -> String {
+    let mut paths: Vec<PathBytes> = Vec::new();
+    let mut ints = Varints::default();
+    let mut lengths = Varints::default();
+    let mut variants = Varints::default();
+    let mut floats = [Floats::default(), Floats::default()];
+
+    for span in &inspection.spans {
+        let path = normalize(&span.path);
+        for (end, _) in path
+            .match_indices(['.', '[', '{'])
+            .chain([(path.len(), "")])
+        {
+            let prefix = &path[..end];
+            let index = match paths.iter().position(|p| p.path == prefix) {
+                Some(index) => index,
+                None => {
+                    paths.push(PathBytes {
+                        path: prefix.to_string(),
+                        ..Default::default()
+                    });
+                    paths.len() - 1
+                }
+            };
+            paths[index].total += span.len;
+            if end == path.len() {
+                paths[index].own += span.len;
+            }
+        }
+
+        let varints = match span.kind {
+            SpanKind::Int => &mut ints,
+            SpanKind::Length => &mut lengths,
+            SpanKind::VariantIndex => &mut variants,
+            SpanKind::Float => {
+                let (floats, ieee) = match resolve(schema, &span.path) {
+                    Some(Schema::F64) => (&mut floats[1], 8),
+                    _ => (&mut floats[0], 4),
+                };
+                floats.count += 1;
+                floats.bytes += span.len;
+                floats.ieee += ieee;
+                continue;
+            }
+            _ => continue,
+        };
+        match span.encoding {
+            Encoding::Byte => varints.byte += 1,
+            Encoding::LiteralVarint => varints.literal += 1,
+            _ => varints.prefixed += 1,
+        }
+    }
+
+    let data: usize = inspection.spans.iter().map(|s| s.len).sum();
+    let mut out = String::new();
+    match inspection.header {
+        Some(_) => writeln!(
+            out,
+            "{} bytes: {} of header, {data} of data",
+            Header::LEN + data,
+            Header::LEN
+        ),
+        None => writeln!(out, "{data} bytes of data"),
+    }
+    .unwrap();
+
+    writeln!(out, "\nbytes by path:").unwrap();
+    let rows: Vec<_> = paths
+        .iter()
+        .map(|p| {
+            let path = if p.path.is_empty() { "." } else { &p.path };
+            let share = p.total as f64 * 100.0 / data.max(1) as f64;
+            (
+                path,
+                p.own.to_string(),
+                p.total.to_string(),
+                format!("{share:.1}%"),
+            )
+        })
+        .collect();
+    let path_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).max(4);
+    let own_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0).max(3);
+    let total_width = rows.iter().map(|r| r.2.len()).max().unwrap_or(0).max(5);
+    writeln!(
+        out,
+        "  {:path_width$}  {:>own_width$}  {:>total_width$}  {:>6}",
+        "path", "own", "total", "share"
+    )
+    .unwrap();
+    for (path, own, total, share) in rows {
+        writeln!(
+            out,
+            "  {path:path_width$}  {own:>own_width$}  {total:>total_width$}  {share:>6}"
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "\nvarints:").unwrap();
+    writeln!(out, "                   byte  literal  prefixed").unwrap();
+    for (name, varints) in [
+        ("integers", ints),
+        ("lengths", lengths),
+        ("variant indexes", variants),
+    ] {
+        writeln!(
+            out,
+            "  {name:15}  {:>5}  {:>7}  {:>8}",
+            varints.byte, varints.literal, varints.prefixed
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "\nfloats:").unwrap();
+    for (name, floats) in ["f32", "f64"].into_iter().zip(floats) {
+        if floats.count == 0 {
+            continue;
+        }
+        let diff = floats.bytes.abs_diff(floats.ieee);
+        let compared = if floats.bytes <= floats.ieee {
+            format!("{diff} less than IEEE 754")
+        } else {
+            format!("{diff} more than IEEE 754")
+        };
+        writeln!(
+            out,
+            "  {} {name}: {} bytes, {compared} ({} bytes)",
+            floats.count, floats.bytes, floats.ieee
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Removes the indexes from a path, so that the elements of a sequence or map share one path, like `.items[].name`.
+fn normalize(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' | '{' => in_index = true,
+            ']' | '}' => in_index = false,
+            _ if in_index => continue,
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Finds the schema of the value at a span path.
+fn resolve<'a>(schema: &'a Schema, path: &str) -> Option<&'a Schema> {
+    if let Schema::Option(inner) = schema {
+        return resolve(inner, path);
+    }
+    if path.is_empty() {
+        return Some(schema);
+    }
+
+    let (part, rest) = next_part(path);
+    let field = |fields: &'a [sbof::schema::Field], part: &str| {
+        let name = part.strip_prefix('.')?;
+        fields.iter().find(|f| f.name == name).map(|f| &f.schema)
+    };
+    let element = |elements: &'a [Schema], part: &str| {
+        elements.get(part.strip_prefix('.')?.parse::<usize>().ok()?)
+    };
+
+    match (schema, part.as_bytes()[0]) {
+        (Schema::Seq(inner), b'[') => resolve(inner, rest),
+        (Schema::Map { key, value }, b'{') => match next_part(rest) {
+            (".key", rest) => resolve(key, rest),
+            (".value", rest) => resolve(value, rest),
+            _ => None,
+        },
+        (Schema::Tuple(elements), b'.') => resolve(element(elements, part)?, rest),
+        (Schema::Struct { fields, .. }, b'.') => resolve(field(fields, part)?, rest),
+        (Schema::Enum { variants, .. }, b'.') => {
+            let variant = variants.iter().find(|v| v.name == part[1..])?;
+            match &variant.kind {
+                VariantKind::Newtype(inner) => resolve(inner, rest),
+                VariantKind::Tuple(elements) => {
+                    let (part, rest) = next_part(rest);
+                    resolve(element(elements, part)?, rest)
+                }
+                VariantKind::Struct(fields) => {
+                    let (part, rest) = next_part(rest);
+                    resolve(field(fields, part)?, rest)
+                }
+                VariantKind::Unit => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Splits off the first part of a non-empty path, like `.name`, `[3]` or `{3}`.
+fn next_part(path: &str) -> (&str, &str) {
+    let end = path
+        .get(1..)
+        .and_then(|rest| rest.find(['.', '[', '{']))
+        .map_or(path.len(), |end| end + 1);
+    path.split_at(end)
+}
+
+#[test]
+fn stats_test() {
+    use sbof::{Value, value::VariantForm};
+
+    let idl = sbof::idl::Idl::parse(
+        "
+        struct Scene { name: str, shapes: seq<Shape>, scale: option<f64> }
+        enum Shape { Point, Circle(f32), Rect { w: u16, h: u16 } }
+        ",
+    )
+    .unwrap();
+    let schema = idl.schema("Scene").unwrap();
+    assert_eq!(resolve(&schema, ".shapes[4].Circle"), Some(&Schema::F32));
+    assert_eq!(resolve(&schema, ".shapes[4].Rect.h"), Some(&Schema::U16));
+    assert_eq!(resolve(&schema, ".scale"), Some(&Schema::F64));
+    assert_eq!(resolve(&schema, ".shapes[4].Rect.d"), None);
+    assert_eq!(normalize(".a[12].b{3}.key"), ".a[].b{}.key");
+
+    let shape = |index, form, variant: &str, value| Value::Enum {
+        index,
+        variant: variant.into(),
+        form,
+        value: Box::new(value),
+    };
+    let scene = Value::Struct(vec![
+        ("name".into(), Value::Str("hi".into())),
+        (
+            "shapes".into(),
+            Value::Seq(vec![
+                shape(1, VariantForm::Newtype, "Circle", Value::F32(1.5)),
+                shape(
+                    2,
+                    VariantForm::Struct,
+                    "Rect",
+                    Value::Struct(vec![
+                        ("w".into(), Value::U16(300)),
+                        ("h".into(), Value::U16(2)),
+                    ]),
+                ),
+                shape(1, VariantForm::Newtype, "Circle", Value::F32(0.5)),
+            ]),
+        ),
+        (
+            "scale".into(),
+            Value::Option(Some(Box::new(Value::F64(2.0)))),
+        ),
+    ]);
+    let bytes = scene.to_bytes_options(&sbof::SbofOptions::new()).unwrap();
+    let inspection = sbof::inspect::inspect(&bytes, &schema).unwrap();
+    assert_eq!(
+        report(&inspection, &schema),
+        "\
+28 bytes: 2 of header, 26 of data
+
+bytes by path:
+  path              own  total   share
+  .                   0     26  100.0%
+  .name               4      4   15.4%
+  .shapes             2     18   69.2%
+  .shapes[]           6     16   61.5%
+  .shapes[].Circle    5      5   19.2%
+  .shapes[].Rect      0      5   19.2%
+  .shapes[].Rect.w    3      3   11.5%
+  .shapes[].Rect.h    2      2    7.7%
+  .scale              4      4   15.4%
+
+varints:
+                   byte  literal  prefixed
+  integers             0        0         2
+  lengths              0        0         2
+  variant indexes      0        0         3
+
+floats:
+  2 f32: 5 bytes, 3 less than IEEE 754 (8 bytes)
+  1 f64: 3 bytes, 5 less than IEEE 754 (8 bytes)
+"
+    );
+}
@@ -1,7 +1,93 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::io::{self, Read};
 
 use crate::{Error, Result};
 
+/// A source of SBOF data that the [`Deserializer`](crate::de::Deserializer) reads from.
+///
+/// This module is private, so the trait is sealed: it names the inputs of the public [`Deserializer`](crate::de::Deserializer) type, but other crates can't implement or call it.
+pub trait Input<'de> {
+    /// Reads a single byte, advancing the input.
+    fn read_u8(&mut self) -> Result<u8>;
+
+    /// Reads a single byte, without advancing the input.
+    fn peek_u8(&mut self) -> Result<u8>;
+
+    /// Reads a single signed byte, advancing the input.
+    fn read_i8(&mut self) -> Result<i8> {
+        Ok(i8::from_le_bytes([self.read_u8()?]))
+    }
+
+    /// Fills `buf` completely, producing [`Error::EOF`] if the input runs out first.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()>;
+
+    /// Reads `len` bytes, borrowing them from the input if possible.
+    fn read_bytes(&mut self, len: usize) -> Result<Bytes<'de>>;
+
+    /// Skips over `len` bytes, producing [`Error::EOF`] if the input runs out first.
+    fn skip(&mut self, len: usize) -> Result<()> {
+        let mut buf = [0; 256];
+        let mut left = len;
+        while left > 0 {
+            let n = left.min(buf.len());
+            self.read_exact(&mut buf[..n])?;
+            left -= n;
+        }
+        Ok(())
+    }
+
+    /// The amount of bytes read so far.
+    fn offset(&self) -> usize;
+
+    /// Starts keeping the bytes that are read, so that they can be retrieved with [`Input::end_capture`]. Captures can be nested.
+    fn begin_capture(&mut self);
+
+    /// Ends the most recent capture, returning the bytes read since `start`, the offset when it began.
+    fn end_capture(&mut self, start: usize) -> Vec<u8>;
+
+    /// Returns true if there are no bytes left in the input.
+    fn is_empty(&mut self) -> Result<bool> {
+        match self.peek_u8() {
+            Ok(_) => Ok(false),
+            Err(Error::EOF) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Bytes read from an [`Input`], either borrowed from the source or copied out of it.
+pub enum Bytes<'de> {
+    Borrowed(&'de [u8]),
+    Owned(Vec<u8>),
+}
+
+impl<'de> Bytes<'de> {
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            Bytes::Borrowed(bytes) => bytes,
+            Bytes::Owned(bytes) => bytes,
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Bytes::Borrowed(bytes) => bytes.to_vec(),
+            Bytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn handle_error<T>(res: io::Result<T>) -> Result<T> {
+    match res {
+        Ok(v) => Ok(v),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(Error::EOF),
+        Err(e) => Err(e)?,
+    }
+}
+
+/// [`Input`] over a borrowed byte slice. Strings and byte arrays are borrowed from the slice without copying.
 pub struct Buf<'src> {
     src: &'src [u8],
     cursor: usize,
@@ -12,54 +98,58 @@ impl<'src> Buf<'src> {
         Buf { src, cursor: 0 }
     }
 
-    fn handle_error<T>(res: io::Result<T>) -> Result<T> {
-        if let Err(e) = res {
-            match e.kind() {
-                std::io::ErrorKind::UnexpectedEof => Err(Error::EOF),
-                _ => Err(e)?,
-            }
-        } else if let Ok(v) = res {
-            Ok(v)
+    /// The bytes that haven't been read yet.
+    pub fn remaining(&self) -> &'src [u8] {
+        &self.src[self.cursor..]
+    }
+
+    pub fn read_slice(&mut self, len: usize) -> Result<&'src [u8]> {
+        if self.src.len() - self.cursor < len {
+            Err(Error::EOF)
         } else {
-            unreachable!()
+            let old = self.cursor;
+            self.cursor += len;
+            Ok(&self.src[old..self.cursor])
         }
     }
+}
 
-    pub fn read_u8(&mut self) -> Result<u8> {
-        let res = self.peek_u8();
+impl<'src> Input<'src> for Buf<'src> {
+    fn read_u8(&mut self) -> Result<u8> {
+        let byte = self.peek_u8()?;
         self.cursor += 1;
-        res
+        Ok(byte)
     }
 
-    pub fn peek_u8(&mut self) -> Result<u8> {
-        let mut buf = [0; 1];
-        Self::handle_error(self.src[self.cursor..].as_ref().read_exact(&mut buf))?;
-        Ok(u8::from_le_bytes(buf))
+    fn peek_u8(&mut self) -> Result<u8> {
+        self.src.get(self.cursor).copied().ok_or(Error::EOF)
     }
 
-    pub fn read_i8(&mut self) -> Result<i8> {
-        let res = self.peek_i8();
-        self.cursor += 1;
-        res
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        buf.copy_from_slice(self.read_slice(buf.len())?);
+        Ok(())
     }
 
-    pub fn peek_i8(&mut self) -> Result<i8> {
-        let mut buf = [0; 1];
-        Self::handle_error(self.src[self.cursor..].as_ref().read_exact(&mut buf))?;
-        Ok(i8::from_le_bytes(buf))
+    fn read_bytes(&mut self, len: usize) -> Result<Bytes<'src>> {
+        self.read_slice(len).map(Bytes::Borrowed)
     }
 
-    pub fn read_slice(&mut self, len: usize) -> Result<&'src [u8]> {
-        if self.src.len() - self.cursor < len {
-            Err(Error::EOF)
-        } else {
-            let old = self.cursor;
-            self.cursor += len;
-            Ok(&self.src[old..self.cursor])
-        }
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.read_slice(len).map(|_| ())
+    }
+
+    fn offset(&self) -> usize {
+        self.cursor
+    }
+
+    fn begin_capture(&mut self) {}
+
+    fn end_capture(&mut self, start: usize) -> Vec<u8> {
+        self.src[start..self.cursor].to_vec()
     }
 }
 
+#[cfg(feature = "std")]
 impl<'src> Read for Buf<'src> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let res = self.src[self.cursor..].as_ref().read(buf);
@@ -69,3 +159,106 @@ impl<'src> Read for Buf<'src> {
         res
     }
 }
+
+/// [`Input`] over any [`Read`] source. Strings and byte arrays are copied out of the reader.
+#[cfg(feature = "std")]
+pub struct IoBuf<R> {
+    reader: R,
+    peeked: Option<u8>,
+    offset: usize,
+
+    /// Bytes read since the outermost capture began
+    capture: Vec<u8>,
+    capture_start: usize,
+    captures: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> IoBuf<R> {
+    pub fn new(reader: R) -> Self {
+        IoBuf {
+            reader,
+            peeked: None,
+            offset: 0,
+            capture: Vec::new(),
+            capture_start: 0,
+            captures: 0,
+        }
+    }
+
+    fn consumed(&mut self, bytes: &[u8]) {
+        self.offset += bytes.len();
+        if self.captures > 0 {
+            self.capture.extend_from_slice(bytes);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: Read> Input<'de> for IoBuf<R> {
+    fn read_u8(&mut self) -> Result<u8> {
+        let byte = self.peek_u8()?;
+        self.peeked = None;
+        self.consumed(&[byte]);
+        Ok(byte)
+    }
+
+    fn peek_u8(&mut self) -> Result<u8> {
+        if let Some(byte) = self.peeked {
+            return Ok(byte);
+        }
+        let mut buf = [0; 1];
+        handle_error(self.reader.read_exact(&mut buf))?;
+        self.peeked = Some(buf[0]);
+        Ok(buf[0])
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let Some((first, rest)) = buf.split_first_mut() else {
+            return Ok(());
+        };
+        match self.peeked.take() {
+            Some(byte) => {
+                *first = byte;
+                handle_error(self.reader.read_exact(rest))?;
+            }
+            None => handle_error(self.reader.read_exact(buf))?,
+        }
+        self.consumed(buf);
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Bytes<'de>> {
+        // The length comes from the input, so don't trust it for the allocation size
+        let mut vec = Vec::new();
+        if len > 0 {
+            if let Some(byte) = self.peeked.take() {
+                vec.push(byte);
+            }
+            let rest = (len - vec.len()) as u64;
+            handle_error(self.reader.by_ref().take(rest).read_to_end(&mut vec))?;
+        }
+        self.consumed(&vec);
+        if vec.len() < len {
+            return Err(Error::EOF);
+        }
+        Ok(Bytes::Owned(vec))
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn begin_capture(&mut self) {
+        if self.captures == 0 {
+            self.capture.clear();
+            self.capture_start = self.offset;
+        }
+        self.captures += 1;
+    }
+
+    fn end_capture(&mut self, start: usize) -> Vec<u8> {
+        self.captures -= 1;
+        self.capture[start - self.capture_start..].to_vec()
+    }
+}
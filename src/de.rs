@@ -1,7 +1,30 @@
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{any::type_name, fmt::Display, marker::PhantomData};
+#[cfg(feature = "std")]
 use std::io::Read;
 
-use crate::{Error, Result, buf::Buf, sign_extend_le};
+use crate::{
+    Error, Header, Result, SbofOptions,
+    error::PathSegment,
+    inspect::{Encoding, Span, SpanKind},
+    schema::{Field, Schema, VariantKind},
+    sign_extend_le,
+    tag::Tag,
+    value::{Value, VariantForm},
+};
+
+#[cfg(feature = "std")]
+use crate::buf::IoBuf;
+use crate::buf::{Buf, Bytes, Input};
 
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
 use serde::{
     Deserialize,
     de::{self, IntoDeserializer, value::U32Deserializer},
@@ -9,45 +32,153 @@ use serde::{
 
 /// Converts a byte array of SBOF data into a value that implements [`Deserialize`].
 pub fn from_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
-    let version = bytes[0];
-    if version > 0 {
-        return Err(Error::UnsupportedVersion);
-    }
-    let high_precision = bytes[1] & (1 << 0) != 0;
-    from_bytes_settings(&bytes[2..], version, high_precision)
+    from_bytes_options(bytes, &SbofOptions::new())
 }
 
 /// Converts a byte array of SBOF data into a value that implements [`Deserialize`], without using a header.
+#[deprecated(note = "use `from_bytes_options` instead")]
 pub fn from_bytes_settings<'de, T: Deserialize<'de>>(
     bytes: &'de [u8],
     version: u8,
     high_precision: bool,
 ) -> Result<T> {
-    let mut deserializer = Deserializer {
-        input: Buf::new(bytes),
-        version,
-        high_precision,
+    let options = SbofOptions::new()
+        .with_header(false)
+        .with_version(version)
+        .with_high_precision(high_precision);
+    from_bytes_options(bytes, &options)
+}
+
+/// Converts a byte array of SBOF data into a value that implements [`Deserialize`], with specific [`SbofOptions`].
+pub fn from_bytes_options<'de, T: Deserialize<'de>>(
+    bytes: &'de [u8],
+    options: &SbofOptions,
+) -> Result<T> {
+    let mut bytes = bytes;
+    let options = if options.header() {
+        options.apply_header(Header::take(&mut bytes)?)
+    } else {
+        *options
+    };
+    let mut deserializer = Deserializer::new(Buf::new(bytes), options);
+    let value = deserializer.deserialize_root()?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Converts the SBOF data at the start of a byte array into a value that implements [`Deserialize`], returning it along with the bytes that come after it. This can be used to read several SBOF values that were written one after another.
+pub fn take_from_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<(T, &'de [u8])> {
+    take_from_bytes_options(bytes, &SbofOptions::new())
+}
+
+/// Converts the SBOF data at the start of a byte array into a value that implements [`Deserialize`], with specific [`SbofOptions`], returning it along with the bytes that come after it. The trailing bytes option is ignored.
+pub fn take_from_bytes_options<'de, T: Deserialize<'de>>(
+    bytes: &'de [u8],
+    options: &SbofOptions,
+) -> Result<(T, &'de [u8])> {
+    let mut bytes = bytes;
+    let options = if options.header() {
+        options.apply_header(Header::take(&mut bytes)?)
+    } else {
+        *options
+    };
+    let mut deserializer = Deserializer::new(Buf::new(bytes), options);
+    let value = deserializer.deserialize_root()?;
+    Ok((value, deserializer.input.remaining()))
+}
+
+/// Reads SBOF data from a [`Read`] source into a value that implements [`DeserializeOwned`]. Strings and byte arrays are copied out of the reader, so borrowed types like `&str` can't be deserialized this way.
+#[cfg(feature = "std")]
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T> {
+    from_reader_options(reader, &SbofOptions::new())
+}
+
+/// Reads SBOF data from a [`Read`] source into a value that implements [`DeserializeOwned`], without using a header.
+#[cfg(feature = "std")]
+#[deprecated(note = "use `from_reader_options` instead")]
+pub fn from_reader_settings<R: Read, T: DeserializeOwned>(
+    reader: R,
+    version: u8,
+    high_precision: bool,
+) -> Result<T> {
+    let options = SbofOptions::new()
+        .with_header(false)
+        .with_version(version)
+        .with_high_precision(high_precision);
+    from_reader_options(reader, &options)
+}
+
+/// Reads SBOF data from a [`Read`] source into a value that implements [`DeserializeOwned`], with specific [`SbofOptions`].
+#[cfg(feature = "std")]
+pub fn from_reader_options<R: Read, T: DeserializeOwned>(
+    reader: R,
+    options: &SbofOptions,
+) -> Result<T> {
+    let mut reader = reader;
+    let options = if options.header() {
+        options.apply_header(Header::read(&mut reader)?)
+    } else {
+        *options
     };
-    T::deserialize(&mut deserializer)
+    let mut deserializer = Deserializer::new(IoBuf::new(reader), options);
+    let value = deserializer.deserialize_root()?;
+    deserializer.end()?;
+    Ok(value)
 }
 
 #[cfg(test)]
 fn from_bytes_testing<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
-    from_bytes_settings(bytes, 0, false)
+    from_bytes_options(bytes, &SbofOptions::new().with_header(false))
+}
+
+/// Converts an integer to a narrower type, producing [`Error::Overflow`] instead of truncating it.
+fn narrow<T: TryFrom<U>, U: Display + Copy>(value: U) -> Result<T> {
+    T::try_from(value).map_err(|_| Error::Overflow {
+        value: value.to_string(),
+        target: type_name::<T>(),
+    })
 }
 
 /// Implementation of [`serde::Deserializer`] for SBOF.
-pub struct Deserializer<'de> {
-    input: Buf<'de>,
+pub struct Deserializer<'de, I: Input<'de> = Buf<'de>> {
+    input: I,
+    options: SbofOptions,
 
-    #[allow(unused)]
-    version: u8,
+    depth: usize,
+    alloc_left: usize,
+    /// Where each of the length prefixed values being read ends, and the depth its contents are read at, innermost last
+    frames: Vec<(usize, usize)>,
 
-    // Feature flags
-    high_precision: bool,
+    marker: PhantomData<&'de ()>,
 }
 
-impl<'de> Deserializer<'de> {
+impl<'de, I: Input<'de>> Deserializer<'de, I> {
+    pub(crate) fn new(input: I, options: SbofOptions) -> Self {
+        Deserializer {
+            input,
+            options,
+            depth: 0,
+            alloc_left: options.max_alloc(),
+            frames: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Deserializes the top-level value, attaching the offset to errors that weren't produced inside of a container.
+    fn deserialize_root<T: Deserialize<'de>>(&mut self) -> Result<T> {
+        T::deserialize(&mut *self).map_err(|e| e.at(self.input.offset()))
+    }
+
+    /// Checks that the input has been used up, if trailing bytes are rejected.
+    pub(crate) fn end(&mut self) -> Result<()> {
+        let reject = self.options.reject_trailing_bytes() || self.options.canonical();
+        if reject && !self.input.is_empty()? {
+            Err(Error::TrailingBytes.at(self.input.offset()))
+        } else {
+            Ok(())
+        }
+    }
+
     fn deserialize_int(&mut self, max_length: u8) -> Result<i128> {
         let byte = self.input.read_u8()?;
         if byte > max_length || byte == 0 {
@@ -55,62 +186,765 @@ impl<'de> Deserializer<'de> {
         } else {
             let mut buf = vec![0; byte as usize];
             self.input.read_exact(&mut buf)?;
-            Ok(sign_extend_le(&buf))
+            let value = sign_extend_le(&buf);
+            if self.options.canonical() {
+                if buf.len() == 1 && !(1..=max_length).contains(&buf[0]) {
+                    return Err(Error::NonCanonical {
+                        reason: "integer has an unnecessary length prefix",
+                    });
+                }
+                if buf.len() > 1 && sign_extend_le(&buf[..buf.len() - 1]) == value {
+                    return Err(Error::NonCanonical {
+                        reason: "integer is longer than necessary",
+                    });
+                }
+            }
+            Ok(value)
         }
     }
 
+    /// Reads an unsigned integer. If `max_length` is [`u8::MAX`], this is an infinitely sized integer, which is always written with a length prefix.
     fn deserialize_uint(&mut self, max_length: u8) -> Result<u128> {
         let byte = self.input.read_u8()?;
         if byte > max_length || byte == 0 {
+            if self.options.canonical() && max_length == u8::MAX {
+                return Err(Error::NonCanonical {
+                    reason: "length is missing its length prefix",
+                });
+            }
             Ok(byte as u128)
+        } else if byte as usize > size_of::<u128>() {
+            Err(Error::Overflow {
+                value: format!("{byte} byte integer"),
+                target: "u128",
+            })
+        } else {
+            let mut buf = [0; 16];
+            let buf_slice = &mut buf[..byte as usize];
+            self.input.read_exact(buf_slice)?;
+            if self.options.canonical() {
+                if buf_slice.len() == 1
+                    && max_length != u8::MAX
+                    && !(1..=max_length).contains(&buf_slice[0])
+                {
+                    return Err(Error::NonCanonical {
+                        reason: "integer has an unnecessary length prefix",
+                    });
+                }
+                if buf_slice.len() > 1 && buf_slice.last() == Some(&0) {
+                    return Err(Error::NonCanonical {
+                        reason: "integer is longer than necessary",
+                    });
+                }
+            }
+            Ok(u128::from_le_bytes(buf))
+        }
+    }
+
+    /// Reads a signed integer with the width of `T`, failing if it doesn't fit in `T`.
+    fn deserialize_int_as<T: TryFrom<i128>>(&mut self) -> Result<T> {
+        let value = self.deserialize_int(size_of::<T>() as u8)?;
+        narrow(value)
+    }
+
+    /// Reads an unsigned integer with the width of `T`, failing if it doesn't fit in `T`.
+    fn deserialize_uint_as<T: TryFrom<u128>>(&mut self) -> Result<T> {
+        let value = self.deserialize_uint(size_of::<T>() as u8)?;
+        narrow(value)
+    }
+
+    fn deserialize_byte_arr(&mut self) -> Result<Bytes<'de>> {
+        let len = self.deserialize_uint(u8::MAX)?; // Infinitely sized integer
+        let limit = self.options.max_bytes_len();
+        if len > limit as u128 {
+            return Err(Error::BytesLengthLimit { len, limit });
+        }
+        let len = usize::try_from(len).expect("length is within the limit");
+        self.alloc_left = self
+            .alloc_left
+            .checked_sub(len)
+            .ok_or(Error::AllocationLimit {
+                limit: self.options.max_alloc(),
+            })?;
+        self.input.read_bytes(len)
+    }
+
+    /// Reads the length of a sequence or map
+    fn deserialize_len(&mut self) -> Result<usize> {
+        let len = self.deserialize_uint(u8::MAX)?; // Infinitely sized integer
+        let limit = self.options.max_seq_len();
+        if len > limit as u128 {
+            return Err(Error::SeqLengthLimit { len, limit });
+        }
+        Ok(usize::try_from(len).expect("length is within the limit"))
+    }
+
+    /// Reads the start of an optional value, returning whether there is a value after it.
+    fn deserialize_is_some(&mut self) -> Result<bool> {
+        match self.input.peek_u8()? {
+            0 => {
+                self.input.read_u8()?;
+                Ok(false)
+            }
+            1 => {
+                self.input.read_u8()?;
+                Ok(true)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// Reads the value of an optional value with `f`, after [`Deserializer::deserialize_is_some`] read its start at `start`.
+    fn deserialize_some<T>(
+        &mut self,
+        start: usize,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        if !self.options.canonical() {
+            return f(self);
+        }
+        let marked = self.input.offset() > start;
+        let first = self.input.peek_u8().ok();
+        let value_start = self.input.offset();
+        let value = f(self)?;
+        // The boolean is only written if the value starts with a 0 or 1, or is empty
+        let empty = self.input.offset() == value_start;
+        if marked && !empty && !matches!(first, Some(0 | 1)) {
+            return Err(Error::NonCanonical {
+                reason: "optional value has an unnecessary boolean",
+            });
+        }
+        if !marked && empty {
+            return Err(Error::NonCanonical {
+                reason: "empty optional value is missing its boolean",
+            });
+        }
+        Ok(value)
+    }
+
+    /// Reads the length prefix of a struct or the data of an enum variant, if the "Skippable" feature flag is enabled. Must be followed by [`Deserializer::end_frame`].
+    fn begin_frame(&mut self) -> Result<()> {
+        if !self.options.framed() {
+            return Ok(());
+        }
+        let end = self.deserialize_end()?;
+        self.frames.push((end, self.depth));
+        Ok(())
+    }
+
+    /// Reads the length prefix of a value, returning the offset where the value ends.
+    fn deserialize_end(&mut self) -> Result<usize> {
+        let len = self.deserialize_uint(u8::MAX)?; // Infinitely sized integer
+        usize::try_from(len)
+            .ok()
+            .and_then(|len| self.input.offset().checked_add(len))
+            .ok_or(Error::Overflow {
+                value: len.to_string(),
+                target: "usize",
+            })
+    }
+
+    /// Skips whatever is left of the value started by [`Deserializer::begin_frame`], like fields that were added after the reader was written. `res` is the result of reading the value.
+    fn end_frame<T>(&mut self, res: Result<T>) -> Result<T> {
+        if !self.options.framed() {
+            return res;
+        }
+        self.pop_frame(res)
+    }
+
+    /// Ends the innermost length prefixed value, skipping whatever is left of it.
+    fn pop_frame<T>(&mut self, res: Result<T>) -> Result<T> {
+        let (end, _) = self.frames.pop().expect("a frame was started");
+        let value = res?;
+        self.skip_to(end)?;
+        Ok(value)
+    }
+
+    /// Reads a length prefixed value with `f`, if the "Skippable" feature flag is enabled.
+    fn framed<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.begin_frame()?;
+        let res = f(self);
+        self.end_frame(res)
+    }
+
+    /// Returns true if there's nothing left of the innermost length prefixed value, or of the input if there isn't one.
+    fn at_value_end(&mut self) -> Result<bool> {
+        match self.frames.last() {
+            Some(&(end, _)) => Ok(self.input.offset() >= end),
+            None => self.input.is_empty(),
+        }
+    }
+
+    /// Skips to `end`, the end of a length prefixed value.
+    fn skip_to(&mut self, end: usize) -> Result<()> {
+        let offset = self.input.offset();
+        if offset > end {
+            return Err(Error::LengthMismatch);
+        }
+        if offset < end && self.options.canonical() {
+            return Err(Error::NonCanonical {
+                reason: "value has bytes after its contents",
+            });
+        }
+        self.input.skip(end - offset)
+    }
+
+    /// Runs `f` one level deeper, checking the depth limit.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let limit = self.options.max_depth();
+        if self.depth >= limit {
+            return Err(Error::DepthLimit { limit });
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+}
+
+impl<'de, I: Input<'de>> Deserializer<'de, I> {
+    /// Deserializes the top-level value with the layout of `schema`.
+    pub(crate) fn deserialize_value_root(&mut self, schema: &Schema) -> Result<Value> {
+        self.deserialize_value(schema)
+            .map_err(|e| e.at(self.input.offset()))
+    }
+
+    /// Deserializes a value with the layout of `schema`. This mirrors what the [`de::Deserializer`] implementation does for the equivalent Rust type.
+    fn deserialize_value(&mut self, schema: &Schema) -> Result<Value> {
+        Ok(match schema {
+            Schema::Bool => Value::Bool(Deserialize::deserialize(&mut *self)?),
+            Schema::U8 => Value::U8(Deserialize::deserialize(&mut *self)?),
+            Schema::U16 => Value::U16(Deserialize::deserialize(&mut *self)?),
+            Schema::U32 => Value::U32(Deserialize::deserialize(&mut *self)?),
+            Schema::U64 => Value::U64(Deserialize::deserialize(&mut *self)?),
+            Schema::U128 => Value::U128(Deserialize::deserialize(&mut *self)?),
+            Schema::I8 => Value::I8(Deserialize::deserialize(&mut *self)?),
+            Schema::I16 => Value::I16(Deserialize::deserialize(&mut *self)?),
+            Schema::I32 => Value::I32(Deserialize::deserialize(&mut *self)?),
+            Schema::I64 => Value::I64(Deserialize::deserialize(&mut *self)?),
+            Schema::I128 => Value::I128(Deserialize::deserialize(&mut *self)?),
+            Schema::F32 => Value::F32(Deserialize::deserialize(&mut *self)?),
+            Schema::F64 => Value::F64(Deserialize::deserialize(&mut *self)?),
+            Schema::Char => Value::Char(Deserialize::deserialize(&mut *self)?),
+            Schema::Str => Value::Str(Deserialize::deserialize(&mut *self)?),
+            Schema::Bytes => Value::Bytes(self.deserialize_byte_arr()?.as_slice().to_vec()),
+            Schema::Unit => Value::Unit,
+            Schema::Option(inner) => {
+                let start = self.input.offset();
+                Value::Option(if self.deserialize_is_some()? {
+                    let value = self.deserialize_some(start, |de| de.deserialize_value(inner))?;
+                    Some(Box::new(value))
+                } else {
+                    None
+                })
+            }
+            Schema::Seq(element) => Value::Seq(self.nested(|de| {
+                let len = de.deserialize_len()?;
+                let mut seq = SbofSeq::new(de, len);
+                let mut values = Vec::new();
+                while let Some(value) = seq.element(|de| de.deserialize_value(element))? {
+                    values.push(value);
+                }
+                Ok(values)
+            })?),
+            Schema::Tuple(elements) => {
+                Value::Tuple(self.nested(|de| de.deserialize_values(elements))?)
+            }
+            Schema::Map { key, value } => Value::Map(self.nested(|de| {
+                let len = de.deserialize_len()?;
+                let mut map = SbofMap::new(de, len);
+                let mut entries = Vec::new();
+                while let Some(k) = map.key(|de| de.deserialize_value(key))? {
+                    entries.push((k, map.value(|de| de.deserialize_value(value))?));
+                }
+                Ok(entries)
+            })?),
+            Schema::Struct { fields, .. } => {
+                Value::Struct(self.nested(|de| de.framed(|de| de.deserialize_fields(fields)))?)
+            }
+            Schema::Enum { variants, .. } => self.nested(|de| {
+                let index = de.deserialize_uint_as::<u32>()?;
+                let variant = variants.get(index as usize).ok_or(Error::InvalidValue {
+                    value: index,
+                    reason: "unknown variant index",
+                })?;
+                let value = de
+                    .framed(|de| match &variant.kind {
+                        VariantKind::Unit => Ok(Value::Unit),
+                        VariantKind::Newtype(inner) => de.deserialize_value(inner),
+                        VariantKind::Tuple(elements) => {
+                            de.deserialize_values(elements).map(Value::Tuple)
+                        }
+                        VariantKind::Struct(fields) => {
+                            de.deserialize_fields(fields).map(Value::Struct)
+                        }
+                    })
+                    .map_err(|e| {
+                        e.in_segment(
+                            de.input.offset(),
+                            PathSegment::Variant(variant.name.clone()),
+                        )
+                    })?;
+                Ok(Value::Enum {
+                    index,
+                    variant: variant.name.clone(),
+                    form: VariantForm::from(&variant.kind),
+                    value: Box::new(value),
+                })
+            })?,
+        })
+    }
+
+    /// Deserializes the elements of a tuple.
+    fn deserialize_values(&mut self, schemas: &[Schema]) -> Result<Vec<Value>> {
+        let mut seq = SbofSeq::new(self, schemas.len());
+        let mut values = Vec::with_capacity(schemas.len());
+        for schema in schemas {
+            values.extend(seq.element(|de| de.deserialize_value(schema))?);
+        }
+        Ok(values)
+    }
+
+    /// Deserializes the fields of a struct.
+    fn deserialize_fields(&mut self, fields: &[Field]) -> Result<Vec<(String, Value)>> {
+        fields
+            .iter()
+            .map(|field| {
+                let value = self.deserialize_value(&field.schema).map_err(|e| {
+                    e.in_segment(self.input.offset(), PathSegment::Field(field.name.clone()))
+                })?;
+                Ok((field.name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+impl<'de, I: Input<'de>> Deserializer<'de, I> {
+    /// Breaks the top-level value with the layout of `schema` down into the byte ranges that make it up.
+    pub(crate) fn inspect_root(&mut self, schema: &Schema) -> Result<Vec<Span>> {
+        let mut spans = Vec::new();
+        self.inspect_value(schema, &mut String::new(), &mut spans)
+            .map_err(|e| e.at(self.input.offset()))?;
+        Ok(spans)
+    }
+
+    /// Reads a value with the layout of `schema`, recording the byte ranges that make it up into `spans`. This mirrors [`Deserializer::deserialize_value`].
+    fn inspect_value(
+        &mut self,
+        schema: &Schema,
+        path: &mut String,
+        spans: &mut Vec<Span>,
+    ) -> Result<()> {
+        let start = self.input.offset();
+        let (kind, value) = match schema {
+            Schema::Bool => (SpanKind::Bool, self.read_bool()?.to_string()),
+            Schema::U8 | Schema::I8 => {
+                let value = match schema {
+                    Schema::U8 => self.input.read_u8()?.to_string(),
+                    _ => self.input.read_i8()?.to_string(),
+                };
+                spans.push(Span {
+                    offset: start,
+                    len: 1,
+                    path: path.clone(),
+                    kind: SpanKind::Int,
+                    encoding: Encoding::Byte,
+                    value,
+                });
+                return Ok(());
+            }
+            Schema::U16 => (
+                SpanKind::Int,
+                self.deserialize_uint_as::<u16>()?.to_string(),
+            ),
+            Schema::U32 => (
+                SpanKind::Int,
+                self.deserialize_uint_as::<u32>()?.to_string(),
+            ),
+            Schema::U64 => (
+                SpanKind::Int,
+                self.deserialize_uint_as::<u64>()?.to_string(),
+            ),
+            Schema::U128 => (SpanKind::Int, self.deserialize_uint(16)?.to_string()),
+            Schema::I16 => (SpanKind::Int, self.deserialize_int_as::<i16>()?.to_string()),
+            Schema::I32 => (SpanKind::Int, self.deserialize_int_as::<i32>()?.to_string()),
+            Schema::I64 => (SpanKind::Int, self.deserialize_int_as::<i64>()?.to_string()),
+            Schema::I128 => (SpanKind::Int, self.deserialize_int(16)?.to_string()),
+            Schema::F32 => (SpanKind::Float, self.read_f32()?.to_string()),
+            Schema::F64 => (SpanKind::Float, self.read_f64()?.to_string()),
+            Schema::Char => (SpanKind::Char, format!("{:?}", self.read_char()?)),
+            Schema::Str | Schema::Bytes => {
+                let bytes = self.deserialize_byte_arr()?;
+                let bytes = bytes.as_slice();
+                let data_start = self.input.offset() - bytes.len();
+                spans.push(Span {
+                    offset: start,
+                    len: data_start - start,
+                    path: path.clone(),
+                    kind: SpanKind::Length,
+                    encoding: Encoding::PrefixedVarint,
+                    value: bytes.len().to_string(),
+                });
+                let (kind, value) = if *schema == Schema::Str {
+                    let str = str::from_utf8(bytes).map_err(|_| Error::InvalidUTF8)?;
+                    (SpanKind::Str, format!("{str:?}"))
+                } else {
+                    let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                    (SpanKind::Bytes, hex.join(" "))
+                };
+                spans.push(Span {
+                    offset: data_start,
+                    len: bytes.len(),
+                    path: path.clone(),
+                    kind,
+                    encoding: Encoding::Raw,
+                    value,
+                });
+                return Ok(());
+            }
+            Schema::Unit => return Ok(()),
+            Schema::Option(inner) => {
+                let is_some = self.deserialize_is_some()?;
+                if self.input.offset() > start {
+                    let value = if is_some { "some" } else { "none" };
+                    self.record(spans, path, start, SpanKind::OptionMarker, value);
+                }
+                if is_some {
+                    self.deserialize_some(start, |de| de.inspect_value(inner, path, spans))?;
+                }
+                return Ok(());
+            }
+            Schema::Seq(element) => {
+                return self.nested(|de| {
+                    let len = de.deserialize_len()?;
+                    de.record(spans, path, start, SpanKind::Length, len);
+                    for index in 0..len {
+                        let len = path.len();
+                        path.push_str(&format!("[{index}]"));
+                        de.inspect_value(element, path, spans)?;
+                        path.truncate(len);
+                    }
+                    Ok(())
+                });
+            }
+            Schema::Tuple(elements) => {
+                return self.nested(|de| de.inspect_values(elements, path, spans));
+            }
+            Schema::Map { key, value } => {
+                return self.nested(|de| {
+                    let len = de.deserialize_len()?;
+                    de.record(spans, path, start, SpanKind::Length, len);
+                    for index in 0..len {
+                        let len = path.len();
+                        path.push_str(&format!("{{{index}}}.key"));
+                        de.inspect_value(key, path, spans)?;
+                        path.truncate(len);
+                        path.push_str(&format!("{{{index}}}.value"));
+                        de.inspect_value(value, path, spans)?;
+                        path.truncate(len);
+                    }
+                    Ok(())
+                });
+            }
+            Schema::Struct { fields, .. } => {
+                return self.nested(|de| {
+                    de.inspect_frame(path, spans)?;
+                    let res = de.inspect_fields(fields, path, spans);
+                    de.end_frame(res)
+                });
+            }
+            Schema::Enum { variants, .. } => {
+                return self.nested(|de| {
+                    let index = de.deserialize_uint_as::<u32>()?;
+                    let variant = variants.get(index as usize).ok_or(Error::InvalidValue {
+                        value: index,
+                        reason: "unknown variant index",
+                    })?;
+                    let value = format!("{index} ({})", variant.name);
+                    de.record(spans, path, start, SpanKind::VariantIndex, value);
+
+                    let len = path.len();
+                    path.push('.');
+                    path.push_str(&variant.name);
+                    de.inspect_frame(path, spans)?;
+                    let res = match &variant.kind {
+                        VariantKind::Unit => Ok(()),
+                        VariantKind::Newtype(inner) => de.inspect_value(inner, path, spans),
+                        VariantKind::Tuple(elements) => de.inspect_values(elements, path, spans),
+                        VariantKind::Struct(fields) => de.inspect_fields(fields, path, spans),
+                    };
+                    de.end_frame(res)?;
+                    path.truncate(len);
+                    Ok(())
+                });
+            }
+        };
+        self.record(spans, path, start, kind, value);
+        Ok(())
+    }
+
+    fn inspect_values(
+        &mut self,
+        schemas: &[Schema],
+        path: &mut String,
+        spans: &mut Vec<Span>,
+    ) -> Result<()> {
+        for (index, schema) in schemas.iter().enumerate() {
+            let len = path.len();
+            path.push_str(&format!(".{index}"));
+            self.inspect_value(schema, path, spans)?;
+            path.truncate(len);
+        }
+        Ok(())
+    }
+
+    fn inspect_fields(
+        &mut self,
+        fields: &[Field],
+        path: &mut String,
+        spans: &mut Vec<Span>,
+    ) -> Result<()> {
+        for field in fields {
+            let len = path.len();
+            path.push('.');
+            path.push_str(&field.name);
+            self.inspect_value(&field.schema, path, spans)?;
+            path.truncate(len);
+        }
+        Ok(())
+    }
+
+    /// Starts a length prefixed value like [`Deserializer::begin_frame`], recording the length prefix.
+    fn inspect_frame(&mut self, path: &str, spans: &mut Vec<Span>) -> Result<()> {
+        let start = self.input.offset();
+        self.begin_frame()?;
+        if let Some(&(end, _)) = self.frames.last()
+            && self.options.framed()
+        {
+            let len = end - self.input.offset();
+            self.record(spans, path, start, SpanKind::Length, len);
+        }
+        Ok(())
+    }
+
+    /// Records the byte range from `start` to the current offset. Lengths and integers other than `u8` and `i8` are varints, so their encoding depends on their size.
+    fn record(
+        &self,
+        spans: &mut Vec<Span>,
+        path: &str,
+        start: usize,
+        kind: SpanKind,
+        value: impl ToString,
+    ) {
+        let len = self.input.offset() - start;
+        let encoding = match kind {
+            SpanKind::Float if self.options.high_precision() => Encoding::IeeeFloat,
+            SpanKind::Float => Encoding::TransformedFloat,
+            SpanKind::Bool | SpanKind::OptionMarker => Encoding::Byte,
+            SpanKind::Str | SpanKind::Bytes => Encoding::Raw,
+            _ if len == 1 => Encoding::LiteralVarint,
+            _ => Encoding::PrefixedVarint,
+        };
+        spans.push(Span {
+            offset: start,
+            len,
+            path: path.to_string(),
+            kind,
+            encoding,
+            value: value.to_string(),
+        });
+    }
+}
+
+impl<'de, I: Input<'de>> Deserializer<'de, I> {
+    fn read_tag(&mut self) -> Result<Tag> {
+        let byte = self.input.read_u8()?;
+        Tag::from_u8(byte).ok_or(Error::InvalidTag {
+            tag: byte,
+            expected: "a type tag",
+        })
+    }
+
+    /// Reads a type tag, failing if it isn't `expected`.
+    fn expect_tag(&mut self, expected: Tag, name: &'static str) -> Result<()> {
+        let byte = self.input.read_u8()?;
+        if byte == expected as u8 {
+            Ok(())
         } else {
-            let mut buf = vec![0; 16];
-            self.input.read_exact(&mut buf[..byte as usize])?;
-            Ok(u128::from_le_bytes(buf.try_into().unwrap()))
+            Err(Error::InvalidTag {
+                tag: byte,
+                expected: name,
+            })
+        }
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
+        let byte = self.input.read_u8()?;
+        match byte {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::InvalidValue {
+                value: byte as u32,
+                reason: "expected bool",
+            }),
+        }
+    }
+
+    fn read_f32(&mut self) -> Result<f32> {
+        if self.options.high_precision() {
+            let mut buf = [0; 4];
+            self.input.read_exact(&mut buf)?;
+            return Ok(f32::from_le_bytes(buf));
+        }
+
+        let significand = self.deserialize_int_as::<i32>()?;
+        // `unsigned_abs` can't overflow on `i32::MIN`, which hostile input can hold
+        let sign = significand.is_negative() as u32;
+        let significand = (significand.unsigned_abs() << 9).reverse_bits();
+        let mantissa = (self.input.read_i8()?.wrapping_add(127)) as u8;
+
+        let bits = significand | (mantissa as u32) << 23 | sign << 31;
+
+        Ok(f32::from_bits(bits))
+    }
+
+    fn read_f64(&mut self) -> Result<f64> {
+        if self.options.high_precision() {
+            let mut buf = [0; 8];
+            self.input.read_exact(&mut buf)?;
+            return Ok(f64::from_le_bytes(buf));
+        }
+
+        let significand = self.deserialize_int_as::<i64>()?;
+        let mantissa = self.deserialize_int_as::<i16>()?;
+        let sign = significand.is_negative() as u64;
+        let significand = (significand.unsigned_abs() << 12).reverse_bits();
+        let mantissa = (mantissa.wrapping_add(1023) & 0x7ff) as u16;
+
+        let bits = significand | (mantissa as u64) << 52 | sign << 63;
+
+        Ok(f64::from_bits(bits))
+    }
+
+    fn read_char(&mut self) -> Result<char> {
+        let int = self.deserialize_uint_as::<u32>()?;
+        char::from_u32(int).ok_or(Error::InvalidValue {
+            value: int,
+            reason: "expected valid character",
+        })
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        String::from_utf8(self.deserialize_byte_arr()?.into_vec()).map_err(|_| Error::InvalidUTF8)
+    }
+
+    fn visit_str<V: de::Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        match self.deserialize_byte_arr()? {
+            Bytes::Borrowed(bytes) => {
+                visitor.visit_borrowed_str(str::from_utf8(bytes).map_err(|_| Error::InvalidUTF8)?)
+            }
+            Bytes::Owned(bytes) => {
+                visitor.visit_string(String::from_utf8(bytes).map_err(|_| Error::InvalidUTF8)?)
+            }
         }
     }
 
-    fn deserialize_byte_arr(&mut self) -> Result<&'de [u8]> {
-        let len = self.deserialize_uint(u8::MAX)? as usize; // Infinitely sized integer
-        self.input.read_slice(len)
+    fn visit_bytes<V: de::Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        match self.deserialize_byte_arr()? {
+            Bytes::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Bytes::Owned(bytes) => visitor.visit_byte_buf(bytes),
+        }
+    }
+
+    /// Deserializes a value by its type tag, when the "Typed" feature flag is enabled.
+    fn deserialize_tagged<V: de::Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        match self.read_tag()? {
+            Tag::Unit => visitor.visit_unit(),
+            Tag::Bool => visitor.visit_bool(self.read_bool()?),
+            Tag::U8 => visitor.visit_u8(self.input.read_u8()?),
+            Tag::U16 => visitor.visit_u16(self.deserialize_uint_as()?),
+            Tag::U32 => visitor.visit_u32(self.deserialize_uint_as()?),
+            Tag::U64 => visitor.visit_u64(self.deserialize_uint_as()?),
+            Tag::U128 => visitor.visit_u128(self.deserialize_uint_as()?),
+            Tag::I8 => visitor.visit_i8(self.input.read_i8()?),
+            Tag::I16 => visitor.visit_i16(self.deserialize_int_as()?),
+            Tag::I32 => visitor.visit_i32(self.deserialize_int_as()?),
+            Tag::I64 => visitor.visit_i64(self.deserialize_int_as()?),
+            Tag::I128 => visitor.visit_i128(self.deserialize_int_as()?),
+            Tag::F32 => visitor.visit_f32(self.read_f32()?),
+            Tag::F64 => visitor.visit_f64(self.read_f64()?),
+            Tag::Char => visitor.visit_char(self.read_char()?),
+            Tag::Str => self.visit_str(visitor),
+            Tag::Bytes => self.visit_bytes(visitor),
+            Tag::None => visitor.visit_none(),
+            Tag::Some => self.nested(|de| visitor.visit_some(de)),
+            Tag::Seq | Tag::Tuple => self.nested(|de| {
+                let len_left = de.deserialize_len()?;
+                visitor.visit_seq(SbofSeq::new(de, len_left))
+            }),
+            Tag::Map => self.nested(|de| {
+                let len_left = de.deserialize_len()?;
+                visitor.visit_map(SbofMap::new(de, len_left))
+            }),
+            Tag::Struct => self.nested(|de| {
+                de.framed(|de| {
+                    let len_left = de.deserialize_len()?;
+                    visitor.visit_map(TypedStruct::new(de, len_left))
+                })
+            }),
+            // Enums look like they do in JSON: unit variants are their name, and other variants are a map from their name to their data
+            Tag::Enum => self.nested(|de| {
+                de.deserialize_uint_as::<u32>()?;
+                let variant = de.read_string()?;
+                de.framed(|de| {
+                    if de.input.peek_u8()? == Tag::Unit as u8 {
+                        de.input.read_u8()?;
+                        visitor.visit_string(variant)
+                    } else {
+                        visitor.visit_map(TypedVariant {
+                            de,
+                            variant: Some(variant),
+                        })
+                    }
+                })
+            }),
+        }
     }
 }
 
-impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+impl<'de, I: Input<'de>> de::Deserializer<'de> for &mut Deserializer<'de, I> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, _: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::Unsupported {
-            name: "deserialize_any",
-            reason: "SBOF is not a self-describing format",
-        })
+        if !self.options.typed() {
+            return Err(Error::Unsupported {
+                name: "deserialize_any",
+                reason: "SBOF is only self-describing with the \"Typed\" feature flag",
+            });
+        }
+        self.deserialize_tagged(visitor)
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let byte = self.input.read_u8()?;
-        let val = match byte {
-            0 => false,
-            1 => true,
-            _ => {
-                return Err(Error::InvalidValue {
-                    value: byte as u32,
-                    reason: "expected bool",
-                });
-            }
-        };
-        visitor.visit_bool(val)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_bool(self.read_bool()?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
         visitor.visit_i8(self.input.read_i8()?)
     }
 
@@ -118,34 +952,49 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i16(self.deserialize_int(2)? as i16)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_i16(self.deserialize_int_as::<i16>()?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i32(self.deserialize_int(4)? as i32)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_i32(self.deserialize_int_as::<i32>()?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i64(self.deserialize_int(8)? as i64)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_i64(self.deserialize_int_as::<i64>()?)
     }
 
     fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i128(self.deserialize_int(16)?)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_i128(self.deserialize_int_as::<i128>()?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
         visitor.visit_u8(self.input.read_u8()?)
     }
 
@@ -153,135 +1002,124 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u16(self.deserialize_uint(2)? as u16)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_u16(self.deserialize_uint_as::<u16>()?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u32(self.deserialize_uint(4)? as u32)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_u32(self.deserialize_uint_as::<u32>()?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u64(self.deserialize_uint(8)? as u64)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_u64(self.deserialize_uint_as::<u64>()?)
     }
 
     fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u128(self.deserialize_uint(16)?)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_u128(self.deserialize_uint_as::<u128>()?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        if self.high_precision {
-            let mut buf = [0; 4];
-            self.input.read_exact(&mut buf)?;
-            return visitor.visit_f32(f32::from_le_bytes(buf));
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
         }
-
-        let significand = self.deserialize_int(4)? as i32;
-        let (sign, significand) = if significand.is_negative() {
-            (1u32, ((-significand << 9).reverse_bits()) as u32)
-        } else {
-            (0u32, (significand << 9).reverse_bits() as u32)
-        };
-        let mantissa = (self.input.read_i8()?.wrapping_add(127)) as u8;
-
-        let bits = significand | (mantissa as u32) << 23 | sign << 31;
-
-        visitor.visit_f32(f32::from_bits(bits))
+        visitor.visit_f32(self.read_f32()?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        if self.high_precision {
-            let mut buf = [0; 8];
-            self.input.read_exact(&mut buf)?;
-            return visitor.visit_f64(f64::from_le_bytes(buf));
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
         }
-
-        let significand = self.deserialize_int(8)? as i64;
-        let mantissa = self.deserialize_int(2)? as i16;
-        let (sign, significand) = if significand.is_negative() {
-            (1u64, ((-significand << 12).reverse_bits()) as u64)
-        } else {
-            (0u64, (significand << 12).reverse_bits() as u64)
-        };
-        let mantissa = (mantissa.wrapping_add(1023) & 0x7ff) as u16;
-
-        let bits = significand | (mantissa as u64) << 52 | sign << 63;
-
-        visitor.visit_f64(f64::from_bits(bits))
+        visitor.visit_f64(self.read_f64()?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let int = self.deserialize_uint(4)? as u32;
-        let char = char::from_u32(int).ok_or(Error::InvalidValue {
-            value: int,
-            reason: "expected valid character",
-        })?;
-        visitor.visit_char(char)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_char(self.read_char()?)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_str(
-            str::from_utf8(self.deserialize_byte_arr()?).map_err(|_| Error::InvalidUTF8)?,
-        )
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        self.visit_str(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_string(
-            str::from_utf8(self.deserialize_byte_arr()?)
-                .map_err(|_| Error::InvalidUTF8)?
-                .to_string(),
-        )
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_string(self.read_string()?)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_bytes(self.deserialize_byte_arr()?)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        self.visit_bytes(visitor)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_byte_buf(self.deserialize_byte_arr()?.to_vec())
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        visitor.visit_byte_buf(self.deserialize_byte_arr()?.into_vec())
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        if self.input.peek_u8()? == 0 {
-            self.input.read_u8()?;
-            visitor.visit_none()
-        } else if self.input.peek_u8()? == 1 {
-            self.input.read_u8()?;
-            visitor.visit_some(self)
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        let start = self.input.offset();
+        if self.deserialize_is_some()? {
+            self.deserialize_some(start, |de| visitor.visit_some(de))
         } else {
-            visitor.visit_some(self)
+            visitor.visit_none()
         }
     }
 
@@ -289,6 +1127,9 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
         visitor.visit_unit()
     }
 
@@ -296,6 +1137,9 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
         visitor.visit_unit()
     }
 
@@ -310,15 +1154,23 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let len_left = self.deserialize_uint(u8::MAX)? as usize;
-        visitor.visit_seq(SbofSeq::new(self, len_left))
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        self.nested(|de| {
+            let len_left = de.deserialize_len()?;
+            visitor.visit_seq(SbofSeq::new(de, len_left))
+        })
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(SbofSeq::new(self, len))
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        self.nested(|de| visitor.visit_seq(SbofSeq::new(de, len)))
     }
 
     fn deserialize_tuple_struct<V>(
@@ -337,9 +1189,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let len_left = self.deserialize_uint(u8::MAX)? as usize;
-        println!("{len_left}");
-        visitor.visit_map(SbofMap::new(self, len_left))
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        self.nested(|de| {
+            let len_left = de.deserialize_len()?;
+            visitor.visit_map(SbofMap::new(de, len_left))
+        })
     }
 
     fn deserialize_struct<V>(
@@ -351,125 +1207,260 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_map(SbofStruct::new(self, fields))
+        if self.options.typed() {
+            return self.deserialize_tagged(visitor);
+        }
+        self.nested(|de| de.framed(|de| visitor.visit_map(SbofStruct::new(de, fields)?)))
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_enum(&mut SbofEnum::new(self))
+        if self.options.typed() {
+            self.expect_tag(Tag::Enum, "an enum")?;
+        }
+        self.nested(|de| {
+            let mut access = SbofEnum::new(de, variants);
+            let res = visitor.visit_enum(&mut access);
+            let res = if access.framed {
+                access.de.end_frame(res)
+            } else {
+                res
+            };
+            res.map_err(|e| match access.variant {
+                Some(variant) => e.in_segment(
+                    access.de.input.offset(),
+                    PathSegment::Variant(variant.into()),
+                ),
+                None => e,
+            })
+        })
     }
 
-    fn deserialize_identifier<V>(self, _: V) -> Result<V::Value>
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::Unsupported {
-            name: "deserialize_identifier",
-            reason: "SBOF is not a self-describing format",
-        })
+        if !self.options.typed() {
+            return Err(Error::Unsupported {
+                name: "deserialize_identifier",
+                reason: "SBOF is only self-describing with the \"Typed\" feature flag",
+            });
+        }
+        self.deserialize_tagged(visitor)
     }
 
-    fn deserialize_ignored_any<V>(self, _: V) -> Result<V::Value>
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::Unsupported {
-            name: "deserialize_ignored_any",
-            reason: "SBOF is not a self-describing format",
-        })
+        if self.options.typed() {
+            self.deserialize_tagged(de::IgnoredAny)?;
+        } else if let Some(&(end, depth)) = self.frames.last()
+            && depth == self.depth
+        {
+            // Without type tags, the value can't be told apart from the rest of the struct or variant, so everything up to its end is ignored
+            self.skip_to(end)?;
+        } else {
+            // Inside of a sequence, map or tuple, skipping to the end of the struct or variant would skip the values after this one too
+            return Err(Error::Unsupported {
+                name: "deserialize_ignored_any",
+                reason: "SBOF is only self-describing with the \"Typed\" feature flag, and values can only be skipped as the fields of structs and the data of variants with the \"Skippable\" feature flag",
+            });
+        }
+        visitor.visit_unit()
     }
 }
 
-struct SbofSeq<'a, 'de> {
-    de: &'a mut Deserializer<'de>,
+struct SbofSeq<'a, 'de, I: Input<'de>> {
+    de: &'a mut Deserializer<'de, I>,
     len_left: usize,
+    index: usize,
 }
 
-impl<'a, 'de> SbofSeq<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, len_left: usize) -> Self {
-        SbofSeq { de, len_left }
+impl<'a, 'de, I: Input<'de>> SbofSeq<'a, 'de, I> {
+    fn new(de: &'a mut Deserializer<'de, I>, len_left: usize) -> Self {
+        SbofSeq {
+            de,
+            len_left,
+            index: 0,
+        }
+    }
+
+    /// Reads the next element with `f`, or returns `None` if there are no elements left.
+    fn element<T>(
+        &mut self,
+        f: impl FnOnce(&mut Deserializer<'de, I>) -> Result<T>,
+    ) -> Result<Option<T>> {
+        if self.len_left == 0 {
+            return Ok(None);
+        }
+        self.len_left -= 1;
+        let index = self.index;
+        self.index += 1;
+        f(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.in_segment(self.de.input.offset(), PathSegment::Index(index)))
     }
 }
 
-impl<'a, 'de> de::SeqAccess<'de> for SbofSeq<'a, 'de> {
+impl<'a, 'de, I: Input<'de>> de::SeqAccess<'de> for SbofSeq<'a, 'de, I> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.len_left == 0 {
-            return Ok(None);
-        }
-        self.len_left -= 1;
-        seed.deserialize(&mut *self.de).map(Some)
+        self.element(|de| seed.deserialize(de))
     }
 }
 
-struct SbofMap<'a, 'de> {
-    de: &'a mut Deserializer<'de>,
+struct SbofMap<'a, 'de, I: Input<'de>> {
+    de: &'a mut Deserializer<'de, I>,
     len_left: usize,
+    index: usize,
+    /// The encoding of the previous key, used to check that keys are in order in canonical mode
+    last_key: Option<Vec<u8>>,
 }
 
-impl<'a, 'de> SbofMap<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, len_left: usize) -> Self {
-        SbofMap { de, len_left }
+impl<'a, 'de, I: Input<'de>> SbofMap<'a, 'de, I> {
+    fn new(de: &'a mut Deserializer<'de, I>, len_left: usize) -> Self {
+        SbofMap {
+            de,
+            len_left,
+            index: 0,
+            last_key: None,
+        }
+    }
+
+    /// Reads the next key with `f`, or returns `None` if there are no entries left.
+    fn key<K>(
+        &mut self,
+        f: impl FnOnce(&mut Deserializer<'de, I>) -> Result<K>,
+    ) -> Result<Option<K>> {
+        if self.len_left == 0 {
+            return Ok(None);
+        }
+        self.len_left -= 1;
+        self.index += 1;
+        let segment = PathSegment::Entry(self.index - 1);
+
+        if !self.de.options.canonical() {
+            return f(&mut *self.de)
+                .map(Some)
+                .map_err(|e| e.in_segment(self.de.input.offset(), segment));
+        }
+
+        let start = self.de.input.offset();
+        self.de.input.begin_capture();
+        let key = f(&mut *self.de);
+        let key_bytes = self.de.input.end_capture(start);
+        let key = key.map_err(|e| e.in_segment(self.de.input.offset(), segment.clone()))?;
+        if self
+            .last_key
+            .as_ref()
+            .is_some_and(|last| *last >= key_bytes)
+        {
+            return Err(Error::NonCanonical {
+                reason: "map keys aren't in ascending order",
+            }
+            .in_segment(self.de.input.offset(), segment));
+        }
+        self.last_key = Some(key_bytes);
+        Ok(Some(key))
+    }
+
+    /// Reads the value of the current entry with `f`.
+    fn value<V>(&mut self, f: impl FnOnce(&mut Deserializer<'de, I>) -> Result<V>) -> Result<V> {
+        f(&mut *self.de)
+            .map_err(|e| e.in_segment(self.de.input.offset(), PathSegment::Entry(self.index - 1)))
     }
 }
 
-impl<'a, 'de> de::MapAccess<'de> for SbofMap<'a, 'de> {
+impl<'a, 'de, I: Input<'de>> de::MapAccess<'de> for SbofMap<'a, 'de, I> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.len_left == 0 {
-            return Ok(None);
-        }
-        self.len_left -= 1;
-        seed.deserialize(&mut *self.de).map(Some)
+        self.key(|de| seed.deserialize(de))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        self.value(|de| seed.deserialize(de))
     }
 }
 
-struct SbofStruct<'a, 'de> {
-    de: &'a mut Deserializer<'de>,
+struct SbofStruct<'a, 'de, I: Input<'de>> {
+    de: &'a mut Deserializer<'de, I>,
     fields: &'static [&'static str],
     cursor: usize,
+    /// How many fields are left to read, if the "Named Fields" feature flag is enabled
+    named_left: Option<usize>,
 }
 
-impl<'a, 'de> SbofStruct<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, fields: &'static [&'static str]) -> Self {
-        SbofStruct {
+impl<'a, 'de, I: Input<'de>> SbofStruct<'a, 'de, I> {
+    fn new(de: &'a mut Deserializer<'de, I>, fields: &'static [&'static str]) -> Result<Self> {
+        let named_left = match de.options.named() {
+            true => Some(de.deserialize_len()?),
+            false => None,
+        };
+        Ok(SbofStruct {
             de,
             fields,
             cursor: 0,
+            named_left,
+        })
+    }
+
+    /// Reads the identifiers of named fields until one of `fields` is found, skipping the others. The found field's value is left in a frame, which is ended by `next_value_seed`.
+    fn next_named(&mut self, mut left: usize) -> Result<Option<usize>> {
+        while left > 0 {
+            left -= 1;
+            let id = self.de.deserialize_uint_as::<u32>()?;
+            let end = self.de.deserialize_end()?;
+            if let Some(index) = self.fields.iter().position(|f| crate::field_id(f) == id) {
+                self.named_left = Some(left);
+                self.de.frames.push((end, self.de.depth));
+                return Ok(Some(index));
+            }
+            // A field that was removed, or added after the reader was written
+            let offset = self.de.input.offset();
+            self.de.input.skip(end - offset)?;
         }
+        self.named_left = Some(0);
+        Ok(None)
     }
 }
 
-impl<'a, 'de> de::MapAccess<'de> for SbofStruct<'a, 'de> {
+impl<'a, 'de, I: Input<'de>> de::MapAccess<'de> for SbofStruct<'a, 'de, I> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.cursor >= self.fields.len() {
+        if let Some(left) = self.named_left {
+            let Some(index) = self.next_named(left)? else {
+                return Ok(None);
+            };
+            self.cursor = index + 1;
+            seed.deserialize(self.fields[index].into_deserializer())
+                .map(Some)
+        } else if self.cursor >= self.fields.len() {
+            Ok(None)
+        } else if self.de.options.default_missing_fields() && self.de.at_value_end()? {
+            // The data was written before the rest of the fields existed, so serde fills in their defaults
             Ok(None)
         } else {
             // Pass the field name to the seed's deserialize method
@@ -483,39 +1474,141 @@ impl<'a, 'de> de::MapAccess<'de> for SbofStruct<'a, 'de> {
     where
         V: de::DeserializeSeed<'de>,
     {
+        let field = self.fields[self.cursor - 1];
+        let mut res = seed.deserialize(&mut *self.de);
+        if self.named_left.is_some() {
+            res = self.de.pop_frame(res);
+        }
+        res.map_err(|e| e.in_segment(self.de.input.offset(), PathSegment::Field(field.into())))
+    }
+}
+
+/// Reads the fields of a struct along with their names, when the "Typed" feature flag is enabled.
+struct TypedStruct<'a, 'de, I: Input<'de>> {
+    de: &'a mut Deserializer<'de, I>,
+    len_left: usize,
+    field: String,
+}
+
+impl<'a, 'de, I: Input<'de>> TypedStruct<'a, 'de, I> {
+    fn new(de: &'a mut Deserializer<'de, I>, len_left: usize) -> Self {
+        TypedStruct {
+            de,
+            len_left,
+            field: String::new(),
+        }
+    }
+}
+
+impl<'a, 'de, I: Input<'de>> de::MapAccess<'de> for TypedStruct<'a, 'de, I> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.len_left == 0 {
+            return Ok(None);
+        }
+        self.len_left -= 1;
+        self.field = self.de.read_string()?;
+        seed.deserialize(self.field.as_str().into_deserializer())
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de).map_err(|e| {
+            e.in_segment(
+                self.de.input.offset(),
+                PathSegment::Field(self.field.clone()),
+            )
+        })
+    }
+}
+
+/// Presents an enum variant with data as a map with a single entry, from the variant's name to its data, when it's read with `deserialize_any`.
+struct TypedVariant<'a, 'de, I: Input<'de>> {
+    de: &'a mut Deserializer<'de, I>,
+    variant: Option<String>,
+}
+
+impl<'a, 'de, I: Input<'de>> de::MapAccess<'de> for TypedVariant<'a, 'de, I> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match &self.variant {
+            Some(variant) => seed
+                .deserialize(variant.as_str().into_deserializer())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = self.variant.take().unwrap_or_default();
         seed.deserialize(&mut *self.de)
+            .map_err(|e| e.in_segment(self.de.input.offset(), PathSegment::Variant(variant)))
     }
 }
 
-struct SbofEnum<'a, 'de> {
-    de: &'a mut Deserializer<'de>,
+struct SbofEnum<'a, 'de, I: Input<'de>> {
+    de: &'a mut Deserializer<'de, I>,
+    variants: &'static [&'static str],
+    variant: Option<&'static str>,
+    /// Whether the variant's data has a length prefix that needs to be ended
+    framed: bool,
 }
 
-impl<'a, 'de> SbofEnum<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        SbofEnum { de }
+impl<'a, 'de, I: Input<'de>> SbofEnum<'a, 'de, I> {
+    fn new(de: &'a mut Deserializer<'de, I>, variants: &'static [&'static str]) -> Self {
+        SbofEnum {
+            de,
+            variants,
+            variant: None,
+            framed: false,
+        }
     }
 }
 
-impl<'a, 'de> de::EnumAccess<'de> for &'a mut SbofEnum<'a, 'de> {
+impl<'a, 'b, 'de, I: Input<'de>> de::EnumAccess<'de> for &'b mut SbofEnum<'a, 'de, I> {
     type Error = Error;
-    type Variant = &'a mut SbofEnum<'a, 'de>;
+    type Variant = Self;
 
     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
     where
         V: de::DeserializeSeed<'de>,
     {
         // Read the variant index from the input
-        let idx = self.de.deserialize_uint(4)? as u32;
+        let idx = self.de.deserialize_uint_as::<u32>()?;
+        if self.de.options.typed() {
+            // The variant's name is only needed by `deserialize_any`
+            self.de.deserialize_byte_arr()?;
+        }
+        self.de.begin_frame()?;
+        self.framed = true;
         let val = seed.deserialize::<U32Deserializer<Error>>(idx.into_deserializer())?;
+        self.variant = self.variants.get(idx as usize).copied();
         Ok((val, self))
     }
 }
 
-impl<'a, 'de> de::VariantAccess<'de> for &'a mut SbofEnum<'a, 'de> {
+impl<'a, 'b, 'de, I: Input<'de>> de::VariantAccess<'de> for &'b mut SbofEnum<'a, 'de, I> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
+        // This may be a `#[serde(other)]` variant, so whatever data the variant has is skipped
+        if self.de.options.typed() {
+            self.de.deserialize_tagged(de::IgnoredAny)?;
+        }
         Ok(())
     }
 
@@ -530,6 +1623,9 @@ impl<'a, 'de> de::VariantAccess<'de> for &'a mut SbofEnum<'a, 'de> {
     where
         V: de::Visitor<'de>,
     {
+        if self.de.options.typed() {
+            return self.de.deserialize_tagged(visitor);
+        }
         visitor.visit_seq(SbofSeq::new(&mut *self.de, len))
     }
 
@@ -537,7 +1633,10 @@ impl<'a, 'de> de::VariantAccess<'de> for &'a mut SbofEnum<'a, 'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_map(SbofStruct::new(&mut *self.de, fields))
+        if self.de.options.typed() {
+            return self.de.deserialize_tagged(visitor);
+        }
+        visitor.visit_map(SbofStruct::new(&mut *self.de, fields)?)
     }
 }
 
@@ -566,7 +1665,12 @@ fn float_test() -> Result<()> {
     assert_eq!(from_bytes_testing::<f32>(&[0x00, 0x00])?, 1.0);
     assert_eq!(from_bytes_testing::<f32>(&[0x05, 0x05])?, 52.0);
     assert_eq!(
-        from_bytes_settings::<f32>(&[0x31, 0x08, 0x64, 0x40], 0, true)?,
+        from_bytes_options::<f32>(
+            &[0x31, 0x08, 0x64, 0x40],
+            &SbofOptions::new()
+                .with_header(false)
+                .with_high_precision(true)
+        )?,
         3.563f32
     );
 
@@ -582,6 +1686,16 @@ fn float_test() -> Result<()> {
         0.2313554863585172f64
     );
 
+    // The most negative significands don't have a positive counterpart
+    assert_eq!(
+        from_bytes_testing::<f32>(&[0x04, 0x00, 0x00, 0x00, 0x80, 0x00])?,
+        -1.0
+    );
+    assert_eq!(
+        from_bytes_testing::<f64>(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00])?,
+        -1.0
+    );
+
     Ok(())
 }
 
@@ -619,3 +1733,584 @@ fn enum_test() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(feature = "std")]
+fn reader_test() -> Result<()> {
+    let bytes = [
+        0x00, 0x00, 0x01, 0x02, b'h', b'i', 0x01, 0x02, 0x03, 0x04, 0x7f,
+    ];
+    let (string, vec, byte): (String, Vec<u8>, u8) = from_reader(bytes.as_slice())?;
+    assert_eq!(string, "hi");
+    assert_eq!(vec, [0x03, 0x04]);
+    assert_eq!(byte, 0x7f);
+
+    let err = from_reader::<_, String>([0x00, 0x00, 0x01, 0x05, b'h'].as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), Error::EOF));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn header_test() -> Result<()> {
+    assert_eq!(from_bytes::<u8>(&[0x00, 0x00, 0x05])?, 5);
+    assert!(matches!(from_bytes::<u8>(&[]), Err(Error::EOF)));
+    assert!(matches!(from_bytes::<u8>(&[0x00]), Err(Error::EOF)));
+    assert!(matches!(
+        from_bytes::<u8>(&[0x00, 0x80, 0x05]),
+        Err(Error::UnknownFeatureFlags { flags: 0x80 })
+    ));
+    assert!(matches!(
+        from_reader::<_, u8>([0x00, 0x40, 0x05].as_slice()),
+        Err(Error::UnknownFeatureFlags { flags: 0x40 })
+    ));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn trailing_bytes_test() -> Result<()> {
+    let bytes = [0x00, 0x00, 0x05, 0x06];
+    assert_eq!(from_bytes::<u8>(&bytes)?, 5);
+
+    let strict = SbofOptions::new().with_reject_trailing_bytes(true);
+    let err = from_bytes_options::<u8>(&bytes, &strict).unwrap_err();
+    assert!(matches!(err.kind(), Error::TrailingBytes));
+    assert_eq!(err.offset(), Some(1));
+    let err = from_reader_options::<_, u8>(bytes.as_slice(), &strict).unwrap_err();
+    assert!(matches!(err.kind(), Error::TrailingBytes));
+    assert_eq!(from_bytes_options::<u16>(&bytes[..3], &strict)?, 5);
+
+    Ok(())
+}
+
+#[test]
+fn take_test() -> Result<()> {
+    let bytes = [0x00, 0x00, 0x01, 0x02, b'h', b'i', 0x00, 0x00, 0x2a];
+    let (first, rest) = take_from_bytes::<&str>(&bytes)?;
+    assert_eq!(first, "hi");
+    let (second, rest) = take_from_bytes::<u32>(rest)?;
+    assert_eq!(second, 42);
+    assert!(rest.is_empty());
+    Ok(())
+}
+
+#[test]
+fn context_test() {
+    #[derive(serde::Deserialize, Debug)]
+    #[allow(unused)]
+    struct Slots {
+        slots: Vec<bool>,
+    }
+
+    #[derive(serde::Deserialize, Debug)]
+    #[allow(unused)]
+    enum Player {
+        Guest,
+        Named { name: String, inventory: Slots },
+    }
+
+    // players[1].Named.inventory.slots[2] is 0x02, which isn't a bool
+    let bytes = [
+        0x01, 0x02, 0x00, 0x01, 0x01, 0x01, 0x01, b'a', 0x01, 0x03, 0x01, 0x00, 0x02,
+    ];
+    let err = from_bytes_testing::<Vec<Player>>(&bytes).unwrap_err();
+    assert!(matches!(err.kind(), Error::InvalidValue { value: 2, .. }));
+    assert_eq!(err.offset(), Some(13));
+    assert_eq!(
+        err.path().unwrap().to_string(),
+        "[1].Named.inventory.slots[2]"
+    );
+    assert_eq!(
+        err.to_string(),
+        "invalid value 2, expected bool at offset 13 (at [1].Named.inventory.slots[2])"
+    );
+
+    let err = from_bytes_testing::<u16>(&[0x02, 0x01]).unwrap_err();
+    assert!(matches!(err.kind(), Error::EOF));
+    assert_eq!(err.offset(), Some(1));
+    assert_eq!(err.path().unwrap().segments(), []);
+}
+
+#[test]
+fn limits_test() -> Result<()> {
+    #[derive(serde::Deserialize, Debug)]
+    #[allow(unused)]
+    enum List {
+        Nil,
+        Cons(u8, Box<List>),
+    }
+
+    let options = SbofOptions::new().with_header(false);
+
+    // A sequence claiming 2^64 - 1 elements
+    let bytes = [0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    let err = from_bytes_options::<Vec<u8>>(&bytes, &options.with_max_seq_len(1024)).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::SeqLengthLimit {
+            len: 0xffffffffffffffff,
+            limit: 1024
+        }
+    ));
+    let err = from_bytes_options::<std::collections::HashMap<u8, u8>>(
+        &bytes,
+        &options.with_max_seq_len(1024),
+    )
+    .unwrap_err();
+    assert!(matches!(err.kind(), Error::SeqLengthLimit { .. }));
+
+    let bytes = [0x01, 0x03, b'a', b'b', b'c'];
+    let err = from_bytes_options::<String>(&bytes, &options.with_max_bytes_len(2)).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::BytesLengthLimit { len: 3, limit: 2 }
+    ));
+    assert_eq!(
+        from_bytes_options::<String>(&bytes, &options.with_max_bytes_len(3))?,
+        "abc"
+    );
+
+    let bytes = [0x01, 0x02, 0x01, 0x02, b'a', b'b', 0x01, 0x02, b'c', b'd'];
+    let err = from_bytes_options::<Vec<String>>(&bytes, &options.with_max_alloc(3)).unwrap_err();
+    assert!(matches!(err.kind(), Error::AllocationLimit { limit: 3 }));
+    assert_eq!(err.path().unwrap().to_string(), "[1]");
+    from_bytes_options::<Vec<String>>(&bytes, &options.with_max_alloc(4))?;
+
+    let bytes = [0x01, 0x01, 0x01, 0x01, 0x01, 0x02, 0x01, 0x01, 0x03, 0x00];
+    let err = from_bytes_options::<List>(&bytes, &options.with_max_depth(3)).unwrap_err();
+    assert!(matches!(err.kind(), Error::DepthLimit { limit: 3 }));
+    from_bytes_options::<List>(&bytes, &options.with_max_depth(4))?;
+
+    // Typed optional values are nested too, so a long run of them can't overflow the stack
+    let mut bytes = vec![0x00, 0x02];
+    bytes.resize(100_000, Tag::Some as u8);
+    let err =
+        from_bytes_options::<serde_json::Value>(&bytes, &SbofOptions::new().with_max_depth(64))
+            .unwrap_err();
+    assert!(matches!(err.kind(), Error::DepthLimit { limit: 64 }));
+
+    Ok(())
+}
+
+#[test]
+fn overflow_test() -> Result<()> {
+    let mut deserializer = Deserializer::new(
+        Buf::new(&[0x02, 0xff, 0xff, 0x02, 0x00, 0x80, 0x04]),
+        SbofOptions::new(),
+    );
+    assert_eq!(deserializer.deserialize_uint_as::<u16>()?, u16::MAX);
+    assert_eq!(deserializer.deserialize_int_as::<i16>()?, i16::MIN);
+    assert_eq!(deserializer.deserialize_uint_as::<u16>()?, 4);
+
+    let err = narrow::<u16, _>(65536u128).unwrap_err();
+    assert_eq!(err.to_string(), "value 65536 overflows u16");
+    let err = narrow::<i8, _>(-129i128).unwrap_err();
+    assert!(matches!(err, Error::Overflow { target: "i8", .. }));
+    assert_eq!(narrow::<u32, _>(7u128)?, 7);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn canonical_test() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    fn non_canonical<T>(res: Result<T>) -> bool {
+        res.is_err_and(|e| matches!(e.kind(), Error::NonCanonical { .. }))
+    }
+
+    let options = SbofOptions::new().with_header(false).with_canonical(true);
+
+    assert_eq!(from_bytes_options::<u16>(&[0x05], &options)?, 5);
+    assert_eq!(
+        from_bytes_options::<i16>(&[0x02, 0x00, 0xff], &options)?,
+        -256
+    );
+    assert_eq!(
+        from_bytes_options::<Vec<u8>>(&[0x01, 0x00], &options)?,
+        Vec::<u8>::new()
+    );
+    assert!(non_canonical(from_bytes_options::<u16>(
+        &[0x01, 0x05],
+        &options
+    )));
+    assert!(non_canonical(from_bytes_options::<u16>(
+        &[0x02, 0x05, 0x00],
+        &options
+    )));
+    assert!(non_canonical(from_bytes_options::<i16>(
+        &[0x02, 0xfb, 0xff],
+        &options
+    )));
+    assert!(non_canonical(from_bytes_options::<Option<u8>>(
+        &[0x01, 0x05],
+        &options
+    )));
+    assert!(non_canonical(from_bytes_options::<(Option<()>, u8)>(
+        &[0x05],
+        &options
+    )));
+    assert!(non_canonical(from_bytes_options::<Vec<u8>>(
+        &[0x00],
+        &options
+    )));
+
+    let map = BTreeMap::from([(1u8, 2u8), (3, 4)]);
+    let bytes = crate::to_bytes_options(&map, &options)?;
+    assert_eq!(
+        from_bytes_options::<BTreeMap<u8, u8>>(&bytes, &options)?,
+        map
+    );
+    assert_eq!(
+        from_reader_options::<_, BTreeMap<u8, u8>>(bytes.as_slice(), &options)?,
+        map
+    );
+
+    let unsorted = [0x01, 0x02, 0x03, 0x04, 0x01, 0x02];
+    assert!(non_canonical(from_bytes_options::<BTreeMap<u8, u8>>(
+        &unsorted, &options
+    )));
+    assert!(non_canonical(from_reader_options::<_, BTreeMap<u8, u8>>(
+        unsorted.as_slice(),
+        &options
+    )));
+    let duplicate = [0x01, 0x02, 0x03, 0x04, 0x03, 0x02];
+    assert!(non_canonical(from_bytes_options::<BTreeMap<u8, u8>>(
+        &duplicate, &options
+    )));
+
+    let err = from_bytes_options::<u8>(&[0x05, 0x00], &options).unwrap_err();
+    assert!(matches!(err.kind(), Error::TrailingBytes));
+
+    Ok(())
+}
+
+#[test]
+fn typed_test() -> Result<()> {
+    use serde::Serialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Untagged {
+        Number(u32),
+        Text(String),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(tag = "type")]
+    enum Internal {
+        Circle { radius: f32 },
+        Square { side: f32 },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Extra {
+        id: u64,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Outer {
+        untagged: Vec<Untagged>,
+        internal: Internal,
+        #[serde(flatten)]
+        extra: Extra,
+        json: serde_json::Value,
+        nothing: Option<()>,
+    }
+
+    let options = SbofOptions::new().with_typed(true);
+    let outer = Outer {
+        untagged: vec![Untagged::Number(5), Untagged::Text("five".into())],
+        internal: Internal::Square { side: 2.5 },
+        extra: Extra { id: 1 << 40 },
+        json: serde_json::json!({ "a": [1, -2, 3.5], "b": null, "c": "d" }),
+        nothing: Some(()),
+    };
+    let bytes = crate::to_bytes_options(&outer, &options)?;
+    assert_eq!(from_bytes_options::<Outer>(&bytes, &options)?, outer);
+
+    // Every value starts with its type tag
+    assert_eq!(
+        crate::to_bytes_options(&300u16, &options)?,
+        [0x00, 0x02, 0x03, 0x02, 0x2c, 0x01]
+    );
+    assert_eq!(
+        crate::to_bytes_options(&Some(1u8), &options)?,
+        [0x00, 0x02, 0x12, 0x02, 0x01]
+    );
+
+    // Fields that the reader doesn't know about are skipped
+    #[derive(Serialize)]
+    struct New {
+        id: u64,
+        name: String,
+        tags: Vec<Untagged>,
+    }
+    let bytes = crate::to_bytes_options(
+        &New {
+            id: 7,
+            name: "new".into(),
+            tags: vec![Untagged::Number(1)],
+        },
+        &options,
+    )?;
+    assert_eq!(
+        from_bytes_options::<Extra>(&bytes, &options)?,
+        Extra { id: 7 }
+    );
+
+    // Without the flag, there are no type tags to go by
+    let err = from_bytes::<serde_json::Value>(&[0x00, 0x00, 0x01]).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::Unsupported {
+            name: "deserialize_any",
+            ..
+        }
+    ));
+
+    let err = from_bytes_options::<String>(&[0x00, 0x02, 0x04, 0x05], &options).unwrap_err();
+    assert!(matches!(err.kind(), Error::Custom(_)));
+    let err = from_bytes_options::<u8>(&[0x00, 0x02, 0xff], &options).unwrap_err();
+    assert!(matches!(err.kind(), Error::InvalidTag { tag: 0xff, .. }));
+
+    Ok(())
+}
+
+#[test]
+fn skippable_test() -> Result<()> {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    enum NewShape {
+        Point,
+        Circle(f32),
+        Polygon { sides: u8, points: Vec<(i16, i16)> },
+    }
+
+    #[derive(Serialize)]
+    struct NewEntry {
+        id: u32,
+        shape: NewShape,
+        label: String,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum OldShape {
+        Point,
+        Circle(f32),
+        #[serde(other)]
+        Unknown,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct OldEntry {
+        id: u32,
+        shape: OldShape,
+    }
+
+    let options = SbofOptions::new().with_skippable(true);
+    let new = vec![
+        NewEntry {
+            id: 1,
+            shape: NewShape::Circle(1.5),
+            label: "circle".into(),
+        },
+        NewEntry {
+            id: 2,
+            shape: NewShape::Polygon {
+                sides: 3,
+                points: vec![(0, 0), (1, 0), (0, 1)],
+            },
+            label: "triangle".into(),
+        },
+    ];
+    for flags in [options, options.with_typed(true)] {
+        let bytes = crate::to_bytes_options(&new, &flags)?;
+        assert_eq!(
+            from_bytes_options::<Vec<OldEntry>>(&bytes, &flags)?,
+            [
+                OldEntry {
+                    id: 1,
+                    shape: OldShape::Circle(1.5),
+                },
+                OldEntry {
+                    id: 2,
+                    shape: OldShape::Unknown,
+                },
+            ]
+        );
+    }
+
+    // Structs and the data of every variant are prefixed with their length in bytes
+    let bytes = crate::to_bytes_options(&(NewShape::Point, 5u8), &options)?;
+    assert_eq!(bytes, [0x00, 0x04, 0x00, 0x01, 0x00, 0x05]);
+    #[derive(Serialize)]
+    struct Id {
+        id: u32,
+    }
+    let bytes = crate::to_bytes_options(&Id { id: 300 }, &options)?;
+    assert_eq!(bytes, [0x00, 0x04, 0x01, 0x03, 0x02, 0x2c, 0x01]);
+
+    let bytes = [0x00, 0x04, 0x01, 0x01, 0x01, 0x00, 0x81, 0x05];
+    let err = from_bytes_options::<(OldShape, u8)>(&bytes, &options).unwrap_err();
+    assert!(matches!(err.kind(), Error::LengthMismatch));
+
+    // The rest of a struct can be ignored, but not an element of a sequence inside of it
+    #[derive(Serialize)]
+    struct Points {
+        points: Vec<(i16, i16)>,
+        id: u8,
+    }
+    #[derive(Deserialize, Debug)]
+    #[allow(unused)]
+    struct Rest {
+        rest: de::IgnoredAny,
+    }
+    #[derive(Deserialize, Debug)]
+    #[allow(unused)]
+    struct Elements {
+        points: Vec<de::IgnoredAny>,
+        id: u8,
+    }
+    let points = Points {
+        points: vec![(1, 2), (3, 4)],
+        id: 5,
+    };
+    let bytes = crate::to_bytes_options(&(&points, 6u8), &options)?;
+    let (_, after) = from_bytes_options::<(Rest, u8)>(&bytes, &options)?;
+    assert_eq!(after, 6);
+    let err = from_bytes_options::<Elements>(&bytes, &options).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::Unsupported {
+            name: "deserialize_ignored_any",
+            ..
+        }
+    ));
+    assert_eq!(err.path().unwrap().to_string(), "points[0]");
+
+    Ok(())
+}
+
+#[test]
+fn default_missing_fields_test() -> Result<()> {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Old {
+        id: u32,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct New {
+        id: u32,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        parent: Option<u32>,
+    }
+
+    let options = SbofOptions::new().with_default_missing_fields(true);
+    let new = |id| New {
+        id,
+        name: String::new(),
+        parent: None,
+    };
+
+    let bytes = crate::to_bytes(&Old { id: 5 })?;
+    assert_eq!(from_bytes_options::<New>(&bytes, &options)?, new(5));
+    assert!(matches!(
+        from_bytes::<New>(&bytes).unwrap_err().kind(),
+        Error::EOF
+    ));
+
+    let options = options.with_skippable(true);
+    let bytes = crate::to_bytes_options(&vec![Old { id: 1 }, Old { id: 2 }], &options)?;
+    assert_eq!(
+        from_bytes_options::<Vec<New>>(&bytes, &options)?,
+        [new(1), new(2)]
+    );
+
+    // Fields without defaults are still needed
+    #[derive(Deserialize, Debug)]
+    #[allow(unused)]
+    struct Required {
+        id: u32,
+        name: String,
+    }
+    let err = from_bytes_options::<Vec<Required>>(&bytes, &options).unwrap_err();
+    assert!(matches!(err.kind(), Error::Custom(_)));
+
+    Ok(())
+}
+
+#[test]
+fn named_fields_test() -> Result<()> {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Old {
+        id: u16,
+        removed: bool,
+        name: String,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct New {
+        name: String,
+        id: u16,
+        #[serde(default)]
+        added: Option<u8>,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Event {
+        Moved { y: i8, x: i8 },
+    }
+
+    let options = SbofOptions::new().with_named_fields(true);
+    let old = Old {
+        id: 300,
+        removed: true,
+        name: "a".into(),
+    };
+    let bytes = crate::to_bytes_options(&old, &options)?;
+    assert_eq!(bytes[..2], [0x00, 0x08]);
+    // 3 fields, then the hash of "id", the length of its value, and its value
+    assert_eq!(
+        bytes[2..14],
+        [
+            0x01, 0x03, 0x04, 0xe0, 0x6a, 0x38, 0x37, 0x01, 0x03, 0x02, 0x2c, 0x01
+        ]
+    );
+    assert_eq!(
+        from_bytes::<New>(&bytes)?,
+        New {
+            name: "a".into(),
+            id: 300,
+            added: None,
+        }
+    );
+
+    let event = Event::Moved { x: -1, y: 2 };
+    for options in [
+        options,
+        options.with_skippable(true),
+        options.with_typed(true),
+    ] {
+        let bytes = crate::to_bytes_options(&event, &options)?;
+        assert_eq!(from_bytes::<Event>(&bytes)?, event);
+    }
+
+    // A value that's longer than its field's length prefix
+    let mut bytes = crate::to_bytes_options(&Event::Moved { x: 1, y: 2 }, &options)?;
+    bytes[11] = 0;
+    let err = from_bytes::<Event>(&bytes).unwrap_err();
+    assert!(matches!(err.kind(), Error::LengthMismatch));
+
+    Ok(())
+}
@@ -1,13 +1,39 @@
-use std::{error, fmt::Display};
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    error,
+    fmt::{self, Display},
+};
 
 use serde::{de, ser};
 
-pub type Result<T, E = Error> = std::result::Result<T, E>;
+pub type Result<T, E = Error> = core::result::Result<T, E>;
 
+/// The errors produced by SBOF.
+///
+/// Errors produced while deserializing are wrapped in [`Error::Context`], which holds where in the input they were produced. Match on [`Error::kind`] instead of the error itself to check which error it is:
+///
+/// ```
+/// use sbof::Error;
+///
+/// let err = sbof::from_bytes::<u16>(&[0x00, 0x00, 0x02, 0x01]).unwrap_err();
+/// assert!(matches!(err.kind(), Error::EOF));
+/// assert!(!matches!(err, Error::EOF));
+/// ```
+///
+/// More errors may be added in minor versions, so matches on them need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Custom error created by serde
     Custom(String),
+    /// Produced when reading from a [`Read`](std::io::Read) source or writing to a [`Write`](std::io::Write) sink fails
+    ///
+    /// This only exists with the `std` feature. Since [`Error`] is `#[non_exhaustive]`, matches on it already need a wildcard arm, so enabling the feature can't break them.
+    #[cfg(feature = "std")]
     Io(std::io::Error),
     /// Produced when an unsupported function is called
     Unsupported {
@@ -17,16 +43,136 @@ pub enum Error {
     /// Produced when an unexpected EOF is found
     EOF,
     /// Produced when an invalid value is found
-    InvalidValue {
-        value: u32,
-        reason: &'static str,
-    },
+    InvalidValue { value: u32, reason: &'static str },
     /// Produced when an unsupported version of SBOF is attempted to be deserialized
     UnsupportedVersion,
+    /// Produced when a header sets feature flags that aren't defined in its version
+    UnknownFeatureFlags { flags: u8 },
     /// Produced when an invalid UTF-8 string is found
     InvalidUTF8,
+    /// Produced when bytes are left over after deserializing a value, and trailing bytes are rejected
+    TrailingBytes,
+    /// Produced when the strings and byte arrays in the data add up to more than the allocation limit
+    AllocationLimit { limit: usize },
+    /// Produced when a sequence or map is longer than the sequence length limit
+    SeqLengthLimit { len: u128, limit: usize },
+    /// Produced when a string or byte array is longer than the byte length limit
+    BytesLengthLimit { len: u128, limit: usize },
+    /// Produced when values are nested deeper than the depth limit
+    DepthLimit { limit: usize },
+    /// Produced when an integer doesn't fit in the type it's being deserialized into
+    Overflow { value: String, target: &'static str },
+    /// Produced with the "Typed" feature flag when a type tag is unknown, or isn't the type that was expected
+    InvalidTag { tag: u8, expected: &'static str },
+    /// Produced with the "Skippable" feature flag when a value is longer than its length prefix
+    LengthMismatch,
+    /// Produced in canonical mode when the data isn't encoded in the canonical form
+    NonCanonical { reason: &'static str },
+    /// Produced when serializing into a slice that's too small for the data
+    BufferFull,
+    /// Produced when a schema file can't be parsed
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// Wraps an error produced while deserializing, with where in the input it was produced. Errors from deserializing are always wrapped, so they never match the other variants directly.
+    Context {
+        error: Box<Error>,
+        offset: usize,
+        path: Path,
+    },
 }
 
+impl Error {
+    /// The error without any context attached. This is what should be matched on, since errors from deserializing are wrapped in [`Error::Context`].
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::Context { error, .. } => error,
+            _ => self,
+        }
+    }
+
+    /// The byte offset in the input (after the header) where the error was produced, if it was produced during deserialization.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::Context { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The path to the value that was being deserialized when the error was produced, like `players[12].inventory.slots[3]`. The path is empty if the error was produced in the top-level value.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Context { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Attaches the offset to the error, unless it already has context.
+    pub(crate) fn at(self, offset: usize) -> Self {
+        match self {
+            Error::Context { .. } => self,
+            error => Error::Context {
+                error: Box::new(error),
+                offset,
+                path: Path::default(),
+            },
+        }
+    }
+
+    /// Attaches the offset to the error if needed, and adds `segment` to the front of its path.
+    pub(crate) fn in_segment(self, offset: usize, segment: PathSegment) -> Self {
+        let mut error = self.at(offset);
+        if let Error::Context { path, .. } = &mut error {
+            path.0.insert(0, segment);
+        }
+        error
+    }
+}
+
+/// A path to a value within the deserialized data, from the top-level value inwards.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Path(Vec<PathSegment>);
+
+impl Path {
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::Entry(index) => write!(f, "{{{index}}}")?,
+                PathSegment::Field(name) | PathSegment::Variant(name) => {
+                    if i > 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{name}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One step in a [`Path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// An element of a sequence or tuple
+    Index(usize),
+    /// An entry of a map, by its position in the map
+    Entry(usize),
+    /// A field of a struct
+    Field(String),
+    /// The variant of an enum
+    Variant(String),
+}
+
+#[cfg(feature = "std")]
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
         Self::Io(value)
@@ -36,7 +182,7 @@ impl From<std::io::Error> for Error {
 impl ser::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
-        T: std::fmt::Display,
+        T: Display,
     {
         Self::Custom(msg.to_string())
     }
@@ -45,14 +191,14 @@ impl ser::Error for Error {
 impl de::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
-        T: std::fmt::Display,
+        T: Display,
     {
         Self::Custom(msg.to_string())
     }
 }
 
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Error::*;
 
         match self {
@@ -60,11 +206,55 @@ impl Display for Error {
             Unsupported { name, reason } => {
                 write!(f, "unsupported function {name} called. {reason}")
             }
+            #[cfg(feature = "std")]
             Io(e) => write!(f, "{e}"),
             EOF => write!(f, "unexpected eof"),
             InvalidValue { value, reason } => write!(f, "invalid value {value}, {reason}"),
             UnsupportedVersion => write!(f, "tried to deserialize unsupported version of SBOF"),
+            UnknownFeatureFlags { flags } => {
+                write!(f, "header sets unknown feature flags {flags:#010b}")
+            }
             InvalidUTF8 => write!(f, "tried to parse invalid UTF-8"),
+            TrailingBytes => write!(f, "found trailing bytes after the value"),
+            AllocationLimit { limit } => {
+                write!(
+                    f,
+                    "strings and byte arrays exceed the allocation limit of {limit} bytes"
+                )
+            }
+            SeqLengthLimit { len, limit } => {
+                write!(
+                    f,
+                    "length {len} exceeds the sequence length limit of {limit}"
+                )
+            }
+            BytesLengthLimit { len, limit } => {
+                write!(f, "length {len} exceeds the byte length limit of {limit}")
+            }
+            DepthLimit { limit } => write!(f, "values are nested deeper than the limit of {limit}"),
+            Overflow { value, target } => write!(f, "value {value} overflows {target}"),
+            InvalidTag { tag, expected } => {
+                write!(f, "invalid type tag {tag:#04x}, expected {expected}")
+            }
+            LengthMismatch => write!(f, "value is longer than its length prefix"),
+            NonCanonical { reason } => write!(f, "non-canonical encoding, {reason}"),
+            BufferFull => write!(f, "the output buffer is full"),
+            Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "{message} at line {line}, column {column}"),
+            Context {
+                error,
+                offset,
+                path,
+            } => {
+                write!(f, "{error} at offset {offset}")?;
+                if !path.0.is_empty() {
+                    write!(f, " (at {path})")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -0,0 +1,214 @@
+use alloc::{format, string::ToString, vec::Vec};
+use core::{
+    fmt::{self, Display},
+    ops::{BitOr, BitOrAssign},
+};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+use crate::{Error, Result};
+
+/// The version of SBOF that this crate reads and writes.
+pub const VERSION: u8 = 0;
+
+/// The set of feature flags stored in the second byte of the header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeatureFlags(u8);
+
+impl FeatureFlags {
+    /// The "High Precision Floats" feature flag.
+    pub const HIGH_PRECISION: FeatureFlags = FeatureFlags(1 << 0);
+
+    /// The "Typed" feature flag.
+    pub const TYPED: FeatureFlags = FeatureFlags(1 << 1);
+
+    /// The "Skippable" feature flag.
+    pub const SKIPPABLE: FeatureFlags = FeatureFlags(1 << 2);
+
+    /// The "Named Fields" feature flag.
+    pub const NAMED_FIELDS: FeatureFlags = FeatureFlags(1 << 3);
+
+    /// Every flag defined in version 0. The rest of the bits are reserved.
+    pub const ALL: FeatureFlags = FeatureFlags(
+        Self::HIGH_PRECISION.0 | Self::TYPED.0 | Self::SKIPPABLE.0 | Self::NAMED_FIELDS.0,
+    );
+
+    /// No flags set.
+    pub const fn empty() -> Self {
+        FeatureFlags(0)
+    }
+
+    /// Creates a set of flags from its byte, or returns `None` if any reserved bits are set.
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !Self::ALL.0 == 0 {
+            Some(FeatureFlags(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Creates a set of flags from its byte, keeping any reserved bits.
+    pub const fn from_bits_retain(bits: u8) -> Self {
+        FeatureFlags(bits)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if every flag in `other` is also set in `self`.
+    pub const fn contains(self, other: FeatureFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: FeatureFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: FeatureFlags) {
+        self.0 &= !other.0;
+    }
+
+    /// Inserts or removes `other` depending on `value`.
+    pub fn set(&mut self, other: FeatureFlags, value: bool) {
+        if value {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
+    }
+}
+
+impl BitOr for FeatureFlags {
+    type Output = FeatureFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        FeatureFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for FeatureFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.insert(rhs);
+    }
+}
+
+impl Display for FeatureFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = Vec::new();
+        if self.contains(FeatureFlags::HIGH_PRECISION) {
+            names.push("High Precision Floats".to_string());
+        }
+        if self.contains(FeatureFlags::TYPED) {
+            names.push("Typed".to_string());
+        }
+        if self.contains(FeatureFlags::SKIPPABLE) {
+            names.push("Skippable".to_string());
+        }
+        if self.contains(FeatureFlags::NAMED_FIELDS) {
+            names.push("Named Fields".to_string());
+        }
+        let reserved = self.0 & !Self::ALL.0;
+        if reserved != 0 {
+            names.push(format!("reserved bits {reserved:#010b}"));
+        }
+
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(", "))
+        }
+    }
+}
+
+/// The header that every SBOF stream starts with: a version byte, followed by a feature flag byte.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Header {
+    pub version: u8,
+    pub flags: FeatureFlags,
+}
+
+impl Header {
+    /// The size of the header in bytes.
+    pub const LEN: usize = 2;
+
+    pub fn new(version: u8, flags: FeatureFlags) -> Self {
+        Header { version, flags }
+    }
+
+    /// Reads a header from the start of `reader`.
+    ///
+    /// Produces [`Error::EOF`] if the header is cut short, [`Error::UnsupportedVersion`] if it is from a newer version of SBOF, and [`Error::UnknownFeatureFlags`] if any reserved feature flag bits are set.
+    #[cfg(feature = "std")]
+    pub fn read<R: Read>(mut reader: R) -> Result<Header> {
+        let mut bytes = [0; Self::LEN];
+        crate::buf::handle_error(reader.read_exact(&mut bytes))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Reads a header from the start of `bytes`, advancing it past the header.
+    pub(crate) fn take(bytes: &mut &[u8]) -> Result<Header> {
+        let (header, rest) = bytes.split_first_chunk().ok_or(Error::EOF)?;
+        let header = Self::from_bytes(*header)?;
+        *bytes = rest;
+        Ok(header)
+    }
+
+    /// Writes the header to `writer`.
+    #[cfg(feature = "std")]
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Parses and validates the header bytes, in the same way as [`Header::read`].
+    pub fn from_bytes(bytes: [u8; Self::LEN]) -> Result<Header> {
+        let [version, flags] = bytes;
+        if version > VERSION {
+            return Err(Error::UnsupportedVersion);
+        }
+        let flags = FeatureFlags::from_bits(flags).ok_or(Error::UnknownFeatureFlags {
+            flags: flags & !FeatureFlags::ALL.bits(),
+        })?;
+        Ok(Header { version, flags })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.version, self.flags.bits()]
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn header_test() -> Result<()> {
+    let header = Header::new(0, FeatureFlags::HIGH_PRECISION);
+    let mut bytes = Vec::new();
+    header.write(&mut bytes)?;
+    assert_eq!(bytes, [0x00, 0x01]);
+    assert_eq!(Header::read(bytes.as_slice())?, header);
+
+    assert!(matches!(Header::read([].as_slice()), Err(Error::EOF)));
+    assert!(matches!(Header::read([0x00].as_slice()), Err(Error::EOF)));
+    assert!(matches!(
+        Header::read([0x01, 0x00].as_slice()),
+        Err(Error::UnsupportedVersion)
+    ));
+    assert!(matches!(
+        Header::read([0x00, 0x81].as_slice()),
+        Err(Error::UnknownFeatureFlags { flags: 0x80 })
+    ));
+    Ok(())
+}
+
+#[test]
+fn flags_display_test() {
+    assert_eq!(FeatureFlags::empty().to_string(), "none");
+    assert_eq!(
+        FeatureFlags::from_bits_retain(0x83).to_string(),
+        "High Precision Floats, Typed, reserved bits 0b10000000"
+    );
+}
@@ -0,0 +1,881 @@
+//! A small language for describing SBOF layouts in `.sbof` files, so that they can be shared without sharing Rust types.
+//!
+//! ```text
+//! // Comments start with two slashes
+//! struct Player {
+//!     name: str,
+//!     level: u16,
+//!     class: Class,
+//!     guild: option<str>,
+//!     inventory: seq<Item>,
+//!     stats: map<str, i32>,
+//!     position: (f32, f32),
+//!     hotbar: [u8; 4],
+//! }
+//!
+//! enum Class {
+//!     Knight,
+//!     Mage(u8),
+//!     Ranger { range: f32, arrows: u32 },
+//! }
+//! ```
+//!
+//! The built-in types are `bool`, `u8` to `u128`, `i8` to `i128`, `f32`, `f64`, `char`, `str`, `bytes` and `()`. Types can refer to each other in any order, but not to themselves, since a [`Schema`] can't describe recursive types. Arrays can hold at most 65536 items, counting the items of arrays nested inside of them, and a type can be made of at most 1048576 values once its arrays and the types it uses are expanded.
+//!
+//! A parsed [`Idl`] can be turned into a [`Schema`] with [`Idl::schema`], or into Rust types with [`Idl::to_rust`]. With the `std` feature, [`compile`] does the latter from a build script:
+//!
+//! ```no_run
+//! # #[cfg(feature = "std")] {
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("game.rs");
+//! sbof::idl::compile("schema/game.sbof", out).unwrap();
+//! # }
+//! ```
+//!
+//! The generated file can then be included with `include!(concat!(env!("OUT_DIR"), "/game.rs"));`.
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+use crate::{
+    Error, Result,
+    schema::{Field, Schema, Variant, VariantKind},
+};
+
+/// A type written in a `.sbof` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Char,
+    Str,
+    Bytes,
+    Unit,
+    Option(Box<Type>),
+    Seq(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Tuple(Vec<Type>),
+    /// An array with a constant length, which is laid out like a tuple
+    Array(Box<Type>, usize),
+    /// A struct or enum defined in the file
+    Named(String),
+}
+
+/// A struct or enum defined in a `.sbof` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDef {
+    pub name: String,
+    pub kind: TypeDefKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDefKind {
+    Struct(Vec<FieldDef>),
+    Enum(Vec<VariantDef>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDef {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantDef {
+    pub name: String,
+    pub data: VariantData,
+}
+
+/// The data a variant holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantData {
+    Unit,
+    Newtype(Type),
+    Tuple(Vec<Type>),
+    Struct(Vec<FieldDef>),
+}
+
+/// The types defined in a `.sbof` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Idl {
+    pub types: Vec<TypeDef>,
+}
+
+impl Idl {
+    /// Parses the contents of a `.sbof` file. Produces [`Error::Parse`] if the syntax is invalid, a type, field or variant is defined twice, an array or type is too large, or a type refers to one that isn't defined or to itself.
+    pub fn parse(src: &str) -> Result<Idl> {
+        let mut parser = Parser::new(src);
+        let mut idl = Idl::default();
+        let mut positions = Vec::new();
+        while parser.peek().is_some() {
+            let (line, column) = parser.position();
+            let def = parser.type_def()?;
+            if idl.get(&def.name).is_some() {
+                return Err(parse_error(
+                    line,
+                    column,
+                    format!("type `{}` is defined more than once", def.name),
+                ));
+            }
+            idl.types.push(def);
+            positions.push((line, column));
+        }
+        let mut checked = BTreeSet::new();
+        let mut sizes = BTreeMap::new();
+        for (def, (line, column)) in idl.types.iter().zip(positions) {
+            idl.check(def, &mut Vec::new(), &mut checked)
+                .map_err(|message| parse_error(line, column, message))?;
+            if idl.def_size(def, &mut sizes) > MAX_SCHEMA_SIZE {
+                return Err(parse_error(
+                    line,
+                    column,
+                    format!(
+                        "type `{}` is larger than the limit of {MAX_SCHEMA_SIZE} values once its arrays and types are expanded",
+                        def.name
+                    ),
+                ));
+            }
+        }
+        Ok(idl)
+    }
+
+    /// The definition of the type called `name`.
+    pub fn get(&self, name: &str) -> Option<&TypeDef> {
+        self.types.iter().find(|def| def.name == name)
+    }
+
+    /// The layout of the type called `name`, or `None` if there is no such type.
+    pub fn schema(&self, name: &str) -> Option<Schema> {
+        self.get(name).map(|def| self.def_schema(def))
+    }
+
+    /// Generates Rust source code with a type for each definition, which derives `Serialize` and `Deserialize` with the same layout. The code needs `serde` with the `derive` feature, and `serde_bytes` if `bytes` is used. Produces [`Error::Unsupported`] if an array has more than 32 items, since serde doesn't implement the traits for longer arrays.
+    pub fn to_rust(&self) -> Result<String> {
+        let mut out =
+            String::from("// Generated from an SBOF schema. Don't edit this file directly.\n");
+        for def in &self.types {
+            out.push_str(
+                "\n#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n",
+            );
+            rename(&mut out, "", &def.name);
+            let name = rust_ident(&def.name);
+            match &def.kind {
+                TypeDefKind::Struct(fields) => {
+                    writeln!(out, "pub struct {name} {{").unwrap();
+                    for field in fields {
+                        rename(&mut out, "    ", &field.name);
+                        writeln!(
+                            out,
+                            "    pub {}: {},",
+                            rust_ident(&field.name),
+                            rust_type(&field.ty)?
+                        )
+                        .unwrap();
+                    }
+                }
+                TypeDefKind::Enum(variants) => {
+                    writeln!(out, "pub enum {name} {{").unwrap();
+                    for variant in variants {
+                        rename(&mut out, "    ", &variant.name);
+                        let name = rust_ident(&variant.name);
+                        match &variant.data {
+                            VariantData::Unit => writeln!(out, "    {name},"),
+                            VariantData::Newtype(ty) => {
+                                writeln!(out, "    {name}({}),", rust_type(ty)?)
+                            }
+                            VariantData::Tuple(types) => {
+                                let types =
+                                    types.iter().map(rust_type).collect::<Result<Vec<_>>>()?;
+                                writeln!(out, "    {name}({}),", types.join(", "))
+                            }
+                            VariantData::Struct(fields) => {
+                                writeln!(out, "    {name} {{").unwrap();
+                                for field in fields {
+                                    let ty = rust_type(&field.ty)?;
+                                    rename(&mut out, "        ", &field.name);
+                                    writeln!(out, "        {}: {ty},", rust_ident(&field.name))
+                                        .unwrap();
+                                }
+                                writeln!(out, "    }},")
+                            }
+                        }
+                        .unwrap();
+                    }
+                }
+            }
+            out.push_str("}\n");
+        }
+        Ok(out)
+    }
+
+    /// Fails if `def` refers to a type that isn't defined, or contains itself. `stack` is the types that contain `def`, and `checked` is the types that are already known to be fine.
+    fn check<'a>(
+        &'a self,
+        def: &'a TypeDef,
+        stack: &mut Vec<&'a str>,
+        checked: &mut BTreeSet<&'a str>,
+    ) -> core::result::Result<(), String> {
+        if checked.contains(def.name.as_str()) {
+            return Ok(());
+        }
+        if stack.contains(&def.name.as_str()) {
+            return Err(format!(
+                "type `{}` contains itself, which a schema can't describe",
+                def.name
+            ));
+        }
+        stack.push(&def.name);
+        for ty in def.types() {
+            for name in ty.names() {
+                let inner = self.get(name).ok_or_else(|| {
+                    format!("type `{name}` is used by `{}`, but isn't defined", def.name)
+                })?;
+                self.check(inner, stack, checked)?;
+            }
+        }
+        stack.pop();
+        checked.insert(&def.name);
+        Ok(())
+    }
+
+    /// How many values the schema of `def` is made of, once arrays and the types it refers to are expanded. Each type is only counted once, so that types used in many places don't take exponential time. `def` must have been checked.
+    fn def_size<'a>(&'a self, def: &'a TypeDef, sizes: &mut BTreeMap<&'a str, usize>) -> usize {
+        if let Some(&size) = sizes.get(def.name.as_str()) {
+            return size;
+        }
+        let size = def
+            .types()
+            .into_iter()
+            .map(|ty| self.type_size(ty, sizes))
+            .fold(1, usize::saturating_add);
+        sizes.insert(&def.name, size);
+        size
+    }
+
+    fn type_size<'a>(&'a self, ty: &'a Type, sizes: &mut BTreeMap<&'a str, usize>) -> usize {
+        let inner = match ty {
+            Type::Option(inner) | Type::Seq(inner) => self.type_size(inner, sizes),
+            Type::Map(key, value) => self
+                .type_size(key, sizes)
+                .saturating_add(self.type_size(value, sizes)),
+            Type::Tuple(types) => types
+                .iter()
+                .map(|ty| self.type_size(ty, sizes))
+                .fold(0, usize::saturating_add),
+            Type::Array(element, len) => len.saturating_mul(self.type_size(element, sizes)),
+            Type::Named(name) => {
+                let def = self.get(name).expect("types were checked");
+                return self.def_size(def, sizes);
+            }
+            _ => 0,
+        };
+        inner.saturating_add(1)
+    }
+
+    fn def_schema(&self, def: &TypeDef) -> Schema {
+        let name = def.name.clone();
+        match &def.kind {
+            TypeDefKind::Struct(fields) => Schema::Struct {
+                name,
+                fields: self.fields_schema(fields),
+            },
+            TypeDefKind::Enum(variants) => Schema::Enum {
+                name,
+                variants: variants
+                    .iter()
+                    .map(|variant| {
+                        let kind = match &variant.data {
+                            VariantData::Unit => VariantKind::Unit,
+                            VariantData::Newtype(ty) => VariantKind::Newtype(self.type_schema(ty)),
+                            VariantData::Tuple(types) => VariantKind::Tuple(
+                                types.iter().map(|ty| self.type_schema(ty)).collect(),
+                            ),
+                            VariantData::Struct(fields) => {
+                                VariantKind::Struct(self.fields_schema(fields))
+                            }
+                        };
+                        Variant::new(variant.name.clone(), kind)
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    fn fields_schema(&self, fields: &[FieldDef]) -> Vec<Field> {
+        fields
+            .iter()
+            .map(|field| Field::new(field.name.clone(), self.type_schema(&field.ty)))
+            .collect()
+    }
+
+    fn type_schema(&self, ty: &Type) -> Schema {
+        match ty {
+            Type::Bool => Schema::Bool,
+            Type::U8 => Schema::U8,
+            Type::U16 => Schema::U16,
+            Type::U32 => Schema::U32,
+            Type::U64 => Schema::U64,
+            Type::U128 => Schema::U128,
+            Type::I8 => Schema::I8,
+            Type::I16 => Schema::I16,
+            Type::I32 => Schema::I32,
+            Type::I64 => Schema::I64,
+            Type::I128 => Schema::I128,
+            Type::F32 => Schema::F32,
+            Type::F64 => Schema::F64,
+            Type::Char => Schema::Char,
+            Type::Str => Schema::Str,
+            Type::Bytes => Schema::Bytes,
+            Type::Unit => Schema::Unit,
+            Type::Option(inner) => Schema::Option(Box::new(self.type_schema(inner))),
+            Type::Seq(element) => Schema::Seq(Box::new(self.type_schema(element))),
+            Type::Map(key, value) => Schema::Map {
+                key: Box::new(self.type_schema(key)),
+                value: Box::new(self.type_schema(value)),
+            },
+            Type::Tuple(types) => {
+                Schema::Tuple(types.iter().map(|ty| self.type_schema(ty)).collect())
+            }
+            Type::Array(element, len) => Schema::Tuple(vec![self.type_schema(element); *len]),
+            Type::Named(name) => self.schema(name).expect("types were checked when parsing"),
+        }
+    }
+}
+
+impl TypeDef {
+    /// The types of the fields and variants of the definition.
+    fn types(&self) -> Vec<&Type> {
+        match &self.kind {
+            TypeDefKind::Struct(fields) => fields.iter().map(|f| &f.ty).collect(),
+            TypeDefKind::Enum(variants) => variants
+                .iter()
+                .flat_map(|v| match &v.data {
+                    VariantData::Unit => Vec::new(),
+                    VariantData::Newtype(ty) => vec![ty],
+                    VariantData::Tuple(types) => types.iter().collect(),
+                    VariantData::Struct(fields) => fields.iter().map(|f| &f.ty).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Type {
+    /// How many items the arrays directly inside of this type hold in total, or 1 if there aren't any.
+    fn array_items(&self) -> usize {
+        match self {
+            Type::Array(element, len) => len.saturating_mul(element.array_items()),
+            Type::Tuple(types) => types
+                .iter()
+                .map(Type::array_items)
+                .fold(1, usize::saturating_add),
+            _ => 1,
+        }
+    }
+
+    /// The names of the defined types that this type refers to.
+    fn names(&self) -> Vec<&str> {
+        match self {
+            Type::Option(inner) | Type::Seq(inner) | Type::Array(inner, _) => inner.names(),
+            Type::Map(key, value) => [key.names(), value.names()].concat(),
+            Type::Tuple(types) => types.iter().flat_map(Type::names).collect(),
+            Type::Named(name) => vec![name],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Parses the `.sbof` file at `input`, and writes Rust types for it to `output`. This is meant to be called from a build script, so it also tells cargo to rerun the build script if `input` changes.
+#[cfg(feature = "std")]
+pub fn compile(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
+    let input = input.as_ref();
+    println!("cargo:rerun-if-changed={}", input.display());
+    let idl = Idl::parse(&fs::read_to_string(input)?)?;
+    fs::write(output, idl.to_rust()?)?;
+    Ok(())
+}
+
+fn parse_error(line: usize, column: usize, message: String) -> Error {
+    Error::Parse {
+        line,
+        column,
+        message,
+    }
+}
+
+/// The most items an array can have, since each item becomes an element of the array's [`Schema::Tuple`].
+const MAX_ARRAY_LEN: usize = 1 << 16;
+
+/// The most values the schema of a type can be made of, counting every item of its arrays, and every use of the types it refers to.
+const MAX_SCHEMA_SIZE: usize = 1 << 20;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "gen", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield",
+];
+
+/// Keywords that can't be raw identifiers, so names that are one of these get an underscore after them instead.
+const RESERVED: &[&str] = &["crate", "self", "super", "Self"];
+
+/// Escapes names that are Rust keywords, which serde still serializes without the `r#`.
+fn rust_ident(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Writes a `#[serde(rename)]` attribute if [`rust_ident`] changed `name`, so that serde still uses the original name.
+fn rename(out: &mut String, indent: &str, name: &str) {
+    if RESERVED.contains(&name) {
+        writeln!(out, "{indent}#[serde(rename = \"{name}\")]").unwrap();
+    }
+}
+
+/// The most items an array can have in generated Rust code, since serde only implements `Serialize` and `Deserialize` for arrays up to this length.
+const MAX_RUST_ARRAY_LEN: usize = 32;
+
+fn rust_type(ty: &Type) -> Result<String> {
+    Ok(match ty {
+        Type::Bool => "bool".into(),
+        Type::U8 => "u8".into(),
+        Type::U16 => "u16".into(),
+        Type::U32 => "u32".into(),
+        Type::U64 => "u64".into(),
+        Type::U128 => "u128".into(),
+        Type::I8 => "i8".into(),
+        Type::I16 => "i16".into(),
+        Type::I32 => "i32".into(),
+        Type::I64 => "i64".into(),
+        Type::I128 => "i128".into(),
+        Type::F32 => "f32".into(),
+        Type::F64 => "f64".into(),
+        Type::Char => "char".into(),
+        Type::Str => "String".into(),
+        // `Vec<u8>` would be serialized as a sequence
+        Type::Bytes => "serde_bytes::ByteBuf".into(),
+        Type::Unit => "()".into(),
+        Type::Option(inner) => format!("Option<{}>", rust_type(inner)?),
+        Type::Seq(element) => format!("Vec<{}>", rust_type(element)?),
+        Type::Map(key, value) => format!(
+            "std::collections::HashMap<{}, {}>",
+            rust_type(key)?,
+            rust_type(value)?
+        ),
+        Type::Tuple(types) if types.len() == 1 => format!("({},)", rust_type(&types[0])?),
+        Type::Tuple(types) => {
+            let types = types.iter().map(rust_type).collect::<Result<Vec<_>>>()?;
+            format!("({})", types.join(", "))
+        }
+        Type::Array(_, len) if *len > MAX_RUST_ARRAY_LEN => {
+            return Err(Error::Unsupported {
+                name: "Idl::to_rust",
+                reason: "serde only implements `Serialize` and `Deserialize` for arrays of up to 32 items",
+            });
+        }
+        Type::Array(element, len) => format!("[{}; {len}]", rust_type(element)?),
+        Type::Named(name) => rust_ident(name),
+    })
+}
+
+/// A recursive descent parser over the characters of a `.sbof` file.
+struct Parser<'a> {
+    chars: core::iter::Peekable<core::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser {
+            chars: src.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(parse_error(self.line, self.column, message.into()))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Skips whitespace and comments, then returns the next character without consuming it.
+    fn peek(&mut self) -> Option<char> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    if ahead.next() != Some('/') {
+                        return Some('/');
+                    }
+                    while self.chars.peek().is_some_and(|&c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                c => return c.copied(),
+            }
+        }
+    }
+
+    /// Consumes `c` if it's next.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error(format!("expected `{c}`"))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+            _ => return self.error("expected a name"),
+        }
+        let mut ident = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            ident.push(c);
+            self.bump();
+        }
+        Ok(ident)
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let mut digits = String::new();
+        if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            while let Some(&c) = self.chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                digits.push(c);
+                self.bump();
+            }
+        }
+        match digits.parse() {
+            Ok(n) => Ok(n),
+            Err(_) => self.error("expected a length"),
+        }
+    }
+
+    /// Parses items separated by commas, with an optional trailing comma, until `close`.
+    fn list<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(item(self)?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn type_def(&mut self) -> Result<TypeDef> {
+        let keyword = self.ident()?;
+        let name = self.ident()?;
+        let kind = match keyword.as_str() {
+            "struct" => {
+                self.expect('{')?;
+                TypeDefKind::Struct(self.fields()?)
+            }
+            "enum" => {
+                self.expect('{')?;
+                let (line, column) = self.position();
+                let variants = self.list('}', Self::variant)?;
+                let mut names = BTreeSet::new();
+                if let Some(variant) = variants.iter().find(|v| !names.insert(&v.name)) {
+                    return Err(parse_error(
+                        line,
+                        column,
+                        format!("variant `{}` is defined more than once", variant.name),
+                    ));
+                }
+                TypeDefKind::Enum(variants)
+            }
+            _ => return self.error(format!("expected `struct` or `enum`, found `{keyword}`")),
+        };
+        Ok(TypeDef { name, kind })
+    }
+
+    /// Parses the fields of a struct, after the opening brace.
+    fn fields(&mut self) -> Result<Vec<FieldDef>> {
+        let (line, column) = self.position();
+        let fields = self.list('}', |parser| {
+            let name = parser.ident()?;
+            parser.expect(':')?;
+            Ok(FieldDef {
+                name,
+                ty: parser.ty()?,
+            })
+        })?;
+        let mut names = BTreeSet::new();
+        if let Some(field) = fields.iter().find(|f| !names.insert(&f.name)) {
+            return Err(parse_error(
+                line,
+                column,
+                format!("field `{}` is defined more than once", field.name),
+            ));
+        }
+        Ok(fields)
+    }
+
+    fn variant(&mut self) -> Result<VariantDef> {
+        let name = self.ident()?;
+        let data = if self.eat('(') {
+            let mut types = self.list(')', Self::ty)?;
+            match types.len() {
+                0 => VariantData::Unit,
+                1 => VariantData::Newtype(types.remove(0)),
+                _ => VariantData::Tuple(types),
+            }
+        } else if self.eat('{') {
+            VariantData::Struct(self.fields()?)
+        } else {
+            VariantData::Unit
+        };
+        Ok(VariantDef { name, data })
+    }
+
+    fn ty(&mut self) -> Result<Type> {
+        if self.eat('(') {
+            let types = self.list(')', Self::ty)?;
+            return Ok(if types.is_empty() {
+                Type::Unit
+            } else {
+                Type::Tuple(types)
+            });
+        }
+        if self.eat('[') {
+            let element = self.ty()?;
+            self.expect(';')?;
+            let len = self.number()?;
+            // Arrays are expanded into a tuple with an element for each item, so nested arrays count every item
+            if len.saturating_mul(element.array_items()) > MAX_ARRAY_LEN {
+                return self.error(format!(
+                    "array is longer than the limit of {MAX_ARRAY_LEN} items"
+                ));
+            }
+            self.expect(']')?;
+            return Ok(Type::Array(Box::new(element), len));
+        }
+
+        let name = self.ident()?;
+        Ok(match name.as_str() {
+            "bool" => Type::Bool,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "u128" => Type::U128,
+            "i8" => Type::I8,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "i64" => Type::I64,
+            "i128" => Type::I128,
+            "f32" => Type::F32,
+            "f64" => Type::F64,
+            "char" => Type::Char,
+            "str" => Type::Str,
+            "bytes" => Type::Bytes,
+            "option" => Type::Option(Box::new(self.generic(1)?.remove(0))),
+            "seq" => Type::Seq(Box::new(self.generic(1)?.remove(0))),
+            "map" => {
+                let mut args = self.generic(2)?;
+                let value = args.remove(1);
+                Type::Map(Box::new(args.remove(0)), Box::new(value))
+            }
+            _ => Type::Named(name),
+        })
+    }
+
+    /// Parses the `len` type arguments of a built-in type, like `<str, u8>`.
+    fn generic(&mut self, len: usize) -> Result<Vec<Type>> {
+        self.expect('<')?;
+        let args = self.list('>', Self::ty)?;
+        if args.len() != len {
+            return self.error(format!(
+                "expected {len} type arguments, found {}",
+                args.len()
+            ));
+        }
+        Ok(args)
+    }
+}
+
+#[test]
+fn idl_test() -> Result<()> {
+    use serde::Deserialize;
+
+    let idl = Idl::parse(
+        "
+        // A player
+        struct Player {
+            name: str,
+            class: Class,
+            guild: option<str>,
+            stats: map<str, i32>,
+            position: (f32, f32),
+            hotbar: [u8; 2],
+            type: u8,
+        }
+
+        enum Class {
+            Knight,
+            Mage(u8),
+            Rogue(u8, bool),
+            Ranger { range: f32, arrows: seq<u32> },
+        }
+        ",
+    )?;
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    struct Player {
+        name: String,
+        class: Class,
+        guild: Option<String>,
+        stats: std::collections::HashMap<String, i32>,
+        position: (f32, f32),
+        hotbar: [u8; 2],
+        r#type: u8,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    enum Class {
+        Knight,
+        Mage(u8),
+        Rogue(u8, bool),
+        Ranger { range: f32, arrows: Vec<u32> },
+    }
+
+    assert_eq!(idl.schema("Player"), Some(Schema::of::<Player>()?));
+    assert_eq!(idl.schema("Missing"), None);
+
+    let rust = idl.to_rust()?;
+    assert!(rust.contains("pub struct Player {\n    pub name: String,\n    pub class: Class,\n"));
+    assert!(rust.contains("    pub stats: std::collections::HashMap<String, i32>,\n"));
+    assert!(rust.contains("    pub hotbar: [u8; 2],\n    pub r#type: u8,\n}\n"));
+    assert!(rust.contains("    Rogue(u8, bool),\n    Ranger {\n        range: f32,\n"));
+
+    // serde doesn't implement its traits for longer arrays
+    assert!(Idl::parse("struct A { a: [u8; 32] }")?.to_rust().is_ok());
+    let err = Idl::parse("enum A { B(option<[u8; 33]>) }")?.to_rust();
+    assert!(matches!(
+        err,
+        Err(Error::Unsupported {
+            name: "Idl::to_rust",
+            ..
+        })
+    ));
+
+    let error = |src| match Idl::parse(src) {
+        Err(Error::Parse { message, .. }) => message,
+        res => panic!("expected a parse error, found {res:?}"),
+    };
+    assert_eq!(
+        error("struct A { a: B }"),
+        "type `B` is used by `A`, but isn't defined"
+    );
+    assert_eq!(
+        error("struct A { a: option<A> }"),
+        "type `A` contains itself, which a schema can't describe"
+    );
+    assert_eq!(
+        error("struct A {}\nenum A {}"),
+        "type `A` is defined more than once"
+    );
+    assert_eq!(error("struct A { a: u8 b: u8 }"), "expected `}`");
+    assert_eq!(
+        error("enum A { B, C(u8), B }"),
+        "variant `B` is defined more than once"
+    );
+    assert_eq!(
+        error("struct A { a: [u8; 99999999999] }"),
+        "array is longer than the limit of 65536 items"
+    );
+    assert_eq!(
+        error("struct A { a: [[u8; 65536]; 65536] }"),
+        "array is longer than the limit of 65536 items"
+    );
+    Idl::parse("struct A { a: [[u8; 256]; 256] }")?;
+    assert_eq!(
+        error("struct B { a: [u8; 65536] }\nstruct A { a: [B; 65536] }"),
+        "type `A` is larger than the limit of 1048576 values once its arrays and types are expanded"
+    );
+    // Each type doubles the size of the one before it
+    let mut diamond = String::from("struct T0 { a: u8 }\n");
+    for i in 1..64 {
+        writeln!(diamond, "struct T{i} {{ a: T{}, b: T{0} }}", i - 1).unwrap();
+    }
+    assert!(error(&diamond).contains("is larger than the limit"));
+    assert!(matches!(
+        Idl::parse("struct A {\n    a: map<u8>,\n}"),
+        Err(Error::Parse {
+            line: 2,
+            column: 15,
+            ..
+        })
+    ));
+
+    Ok(())
+}
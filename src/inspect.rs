@@ -0,0 +1,186 @@
+//! Breaks SBOF data down into the byte ranges that make it up, for debugging data by hand and seeing where its bytes go.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    Header, Result, SbofOptions,
+    buf::Buf,
+    de::Deserializer,
+    schema::Schema,
+    value::{NAMED_UNSUPPORTED, TYPED_UNSUPPORTED},
+};
+
+/// SBOF data broken down into byte ranges, by [`inspect`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inspection {
+    /// The header, if the data has one
+    pub header: Option<Header>,
+    /// The byte ranges of the data after the header, in order
+    pub spans: Vec<Span>,
+}
+
+/// A range of bytes that stores one part of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// Where the range starts, counting the header
+    pub offset: usize,
+    pub len: usize,
+    /// The path to the value the range is part of, like `.items[3].name`. The path is empty for the top-level value.
+    pub path: String,
+    pub kind: SpanKind,
+    pub encoding: Encoding,
+    /// The decoded contents of the range, like `300` or `"house"`
+    pub value: String,
+}
+
+/// What part of a value a [`Span`] stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Bool,
+    Int,
+    Float,
+    Char,
+    /// The contents of a string, after its length prefix
+    Str,
+    /// The contents of a byte array, after its length prefix
+    Bytes,
+    /// The length of a string, byte array, sequence or map, or with the "Skippable" feature flag, of a struct or the data of an enum variant
+    Length,
+    /// The boolean in front of an optional value
+    OptionMarker,
+    /// The index of an enum variant
+    VariantIndex,
+}
+
+/// How the bytes of a [`Span`] are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// A single byte, like a `u8` or a boolean
+    Byte,
+    /// A varint stored as a single byte, without a length byte in front of it
+    LiteralVarint,
+    /// A varint with a length byte in front of it
+    PrefixedVarint,
+    /// A float stored as its significand and exponent
+    TransformedFloat,
+    /// A float stored as IEEE 754, with the "High Precision Floats" feature flag
+    IeeeFloat,
+    /// The raw contents of a string or byte array
+    Raw,
+}
+
+/// Breaks SBOF data, including its header, down into byte ranges with the layout of `schema`.
+pub fn inspect(bytes: &[u8], schema: &Schema) -> Result<Inspection> {
+    inspect_options(bytes, schema, &SbofOptions::new())
+}
+
+/// Breaks SBOF data down into byte ranges with the layout of `schema`, with specific [`SbofOptions`]. Data with the "Typed" or "Named Fields" feature flags isn't supported.
+pub fn inspect_options(bytes: &[u8], schema: &Schema, options: &SbofOptions) -> Result<Inspection> {
+    let mut data = bytes;
+    let (header, options) = if options.header() {
+        let header = Header::take(&mut data)?;
+        (Some(header), options.apply_header(header))
+    } else {
+        (None, *options)
+    };
+    if options.typed() {
+        return Err(TYPED_UNSUPPORTED);
+    }
+    if options.named_fields() {
+        return Err(NAMED_UNSUPPORTED);
+    }
+
+    let mut deserializer = Deserializer::new(Buf::new(data), options);
+    let mut spans = deserializer.inspect_root(schema)?;
+    deserializer.end()?;
+    let header_len = bytes.len() - data.len();
+    for span in &mut spans {
+        span.offset += header_len;
+    }
+    Ok(Inspection { header, spans })
+}
+
+#[test]
+fn inspect_test() -> Result<()> {
+    use crate::schema::{Field, Variant, VariantKind};
+
+    #[derive(serde::Serialize)]
+    enum Shape {
+        Circle(f32),
+    }
+
+    #[derive(serde::Serialize)]
+    struct Drawing {
+        name: String,
+        layer: u16,
+        shapes: Vec<Shape>,
+        hidden: Option<bool>,
+    }
+
+    let schema = Schema::Struct {
+        name: "Drawing".into(),
+        fields: vec![
+            Field::new("name", Schema::Str),
+            Field::new("layer", Schema::U16),
+            Field::new(
+                "shapes",
+                Schema::Seq(Box::new(Schema::Enum {
+                    name: "Shape".into(),
+                    variants: vec![Variant::new("Circle", VariantKind::Newtype(Schema::F32))],
+                })),
+            ),
+            Field::new("hidden", Schema::Option(Box::new(Schema::Bool))),
+        ],
+    };
+    let drawing = Drawing {
+        name: "hi".into(),
+        layer: 300,
+        shapes: vec![Shape::Circle(1.5)],
+        hidden: Some(false),
+    };
+    let bytes = crate::to_bytes(&drawing)?;
+    let inspection = inspect(&bytes, &schema)?;
+    assert_eq!(inspection.header, Some(Header::default()));
+
+    let spans: Vec<_> = inspection
+        .spans
+        .iter()
+        .map(|s| {
+            (
+                s.offset,
+                s.len,
+                s.path.as_str(),
+                s.kind,
+                s.encoding,
+                s.value.as_str(),
+            )
+        })
+        .collect();
+    use {Encoding::*, SpanKind::*};
+    assert_eq!(
+        spans,
+        [
+            (2, 2, ".name", Length, PrefixedVarint, "2"),
+            (4, 2, ".name", Str, Raw, "\"hi\""),
+            (6, 3, ".layer", Int, PrefixedVarint, "300"),
+            (9, 2, ".shapes", Length, PrefixedVarint, "1"),
+            (
+                11,
+                1,
+                ".shapes[0]",
+                VariantIndex,
+                LiteralVarint,
+                "0 (Circle)"
+            ),
+            (12, 3, ".shapes[0].Circle", Float, TransformedFloat, "1.5"),
+            (15, 1, ".hidden", OptionMarker, Byte, "some"),
+            (16, 1, ".hidden", Bool, Byte, "false"),
+        ]
+    );
+    assert_eq!(
+        inspection.spans.iter().map(|s| s.len).sum::<usize>(),
+        bytes.len() - Header::LEN
+    );
+
+    Ok(())
+}
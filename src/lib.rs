@@ -1,12 +1,44 @@
+//! An implementation of SBOF, the Small Binary Object Format, using serde. See the README for the format itself.
+//!
+//! The `std` feature, which is enabled by default, adds reading from `std::io::Read` sources and writing to `std::io::Write` sinks. Without it, the crate is `no_std`, but it still needs `alloc`: errors hold strings, and [`to_bytes`], [`Value`] and the schemas are built on `Vec`. There is no `alloc` feature to turn that off.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod de;
 pub mod error;
+pub mod idl;
+pub mod inspect;
+pub mod schema;
 pub mod ser;
+pub mod value;
 
 mod buf;
+mod header;
+mod options;
+mod tag;
 
-pub use de::{from_bytes, from_bytes_settings};
-pub use error::{Error, Result};
-pub use ser::{to_bytes, to_bytes_settings};
+#[allow(deprecated)]
+pub use de::{
+    from_bytes, from_bytes_options, from_bytes_settings, take_from_bytes, take_from_bytes_options,
+};
+#[cfg(feature = "std")]
+#[allow(deprecated)]
+pub use de::{from_reader, from_reader_options, from_reader_settings};
+pub use error::{Error, Path, PathSegment, Result};
+pub use header::{FeatureFlags, Header, VERSION};
+pub use options::SbofOptions;
+#[allow(deprecated)]
+pub use ser::{
+    serialized_size, to_bytes, to_bytes_options, to_bytes_settings, to_slice, to_slice_options,
+};
+#[cfg(feature = "std")]
+#[allow(deprecated)]
+pub use ser::{to_writer, to_writer_options, to_writer_settings};
+pub use value::Value;
+
+use alloc::{vec, vec::Vec};
 
 fn sign_extend_le(bytes: &[u8]) -> i128 {
     if bytes.len() > 16 || bytes.is_empty() {
@@ -25,8 +57,22 @@ fn sign_extend_le(bytes: &[u8]) -> i128 {
     i128::from_le_bytes(vec.try_into().unwrap())
 }
 
+/// The identifier of a struct field with the "Named Fields" feature flag, which is the 32-bit FNV-1a hash of its name.
+fn field_id(name: &str) -> u32 {
+    name.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
 #[test]
 fn sign_extend_test() {
     assert_eq!(sign_extend_le((5u8).to_le_bytes().as_slice()), 5);
     assert_eq!(sign_extend_le((-25i8).to_le_bytes().as_slice()), -25);
 }
+
+#[test]
+fn field_id_test() {
+    assert_eq!(field_id(""), 0x811c9dc5);
+    assert_eq!(field_id("a"), 0xe40c292c);
+    assert_eq!(field_id("foobar"), 0xbf9cf968);
+}
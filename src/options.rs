@@ -0,0 +1,206 @@
+use crate::{FeatureFlags, Header, VERSION};
+
+/// Settings shared by serialization and deserialization. Use the `with_*` methods to build a set of options, starting from [`SbofOptions::new`].
+///
+/// By default, there is a header, the version is 0, every feature flag is disabled, and there are no limits. When deserializing untrusted data, the limits should be set with [`SbofOptions::with_max_alloc`], [`SbofOptions::with_max_seq_len`], [`SbofOptions::with_max_bytes_len`] and [`SbofOptions::with_max_depth`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SbofOptions {
+    header: bool,
+    version: u8,
+    flags: FeatureFlags,
+    reject_trailing_bytes: bool,
+    canonical: bool,
+    default_missing_fields: bool,
+
+    // Limits
+    max_alloc: usize,
+    max_seq_len: usize,
+    max_bytes_len: usize,
+    max_depth: usize,
+}
+
+impl Default for SbofOptions {
+    fn default() -> Self {
+        SbofOptions {
+            header: true,
+            version: VERSION,
+            flags: FeatureFlags::empty(),
+            reject_trailing_bytes: false,
+            canonical: false,
+            default_missing_fields: false,
+            max_alloc: usize::MAX,
+            max_seq_len: usize::MAX,
+            max_bytes_len: usize::MAX,
+            max_depth: usize::MAX,
+        }
+    }
+}
+
+impl SbofOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the data starts with a header. When deserializing data with a header, the version and feature flags are read from the header instead of these options.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Sets the version of SBOF being used.
+    pub fn with_version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets every feature flag at once.
+    pub fn with_feature_flags(mut self, flags: FeatureFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the "High Precision Floats" feature flag.
+    pub fn with_high_precision(mut self, high_precision: bool) -> Self {
+        self.flags.set(FeatureFlags::HIGH_PRECISION, high_precision);
+        self
+    }
+
+    /// Sets the "Typed" feature flag. Every value is prefixed with a type tag, which makes the data self-describing, so that `deserialize_any` and the serde features that depend on it work, like `#[serde(untagged)]` and `#[serde(flatten)]`.
+    pub fn with_typed(mut self, typed: bool) -> Self {
+        self.flags.set(FeatureFlags::TYPED, typed);
+        self
+    }
+
+    /// Sets the "Skippable" feature flag. Structs and the data of enum variants are prefixed with their length in bytes, so that readers can skip fields that were added after them, and variants they don't know about with `#[serde(other)]`. This has no effect on typed data, which can already be skipped.
+    pub fn with_skippable(mut self, skippable: bool) -> Self {
+        self.flags.set(FeatureFlags::SKIPPABLE, skippable);
+        self
+    }
+
+    /// Sets the "Named Fields" feature flag. Struct fields are identified by a hash of their name instead of their position, so that readers can handle fields that were reordered, removed, or added anywhere in the struct. This has no effect on typed data, which already stores field names.
+    pub fn with_named_fields(mut self, named_fields: bool) -> Self {
+        self.flags.set(FeatureFlags::NAMED_FIELDS, named_fields);
+        self
+    }
+
+    /// Sets whether deserialization fails with [`Error::TrailingBytes`](crate::Error::TrailingBytes) if there are bytes left over after the value.
+    pub fn with_reject_trailing_bytes(mut self, reject: bool) -> Self {
+        self.reject_trailing_bytes = reject;
+        self
+    }
+
+    /// Sets canonical mode. When serializing, map entries are sorted by the bytes of their keys, so that equal values always produce the same bytes. When deserializing, anything other than the canonical encoding is rejected with [`Error::NonCanonical`](crate::Error::NonCanonical), and trailing bytes are rejected. This is useful when the bytes are hashed or signed.
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Sets whether a struct may end early when deserializing, so that fields added to the end of a struct with `#[serde(default)]` can be read from data written before they existed. A struct ends early when its length prefix runs out with the "Skippable" feature flag, or when the input runs out.
+    pub fn with_default_missing_fields(mut self, default: bool) -> Self {
+        self.default_missing_fields = default;
+        self
+    }
+
+    /// Sets the maximum amount of bytes that all of the strings and byte arrays in the data may add up to. Exceeding this produces [`Error::AllocationLimit`](crate::Error::AllocationLimit).
+    pub fn with_max_alloc(mut self, max_alloc: usize) -> Self {
+        self.max_alloc = max_alloc;
+        self
+    }
+
+    /// Sets the maximum amount of elements in a single sequence, or entries in a single map. Exceeding this produces [`Error::SeqLengthLimit`](crate::Error::SeqLengthLimit).
+    pub fn with_max_seq_len(mut self, max_seq_len: usize) -> Self {
+        self.max_seq_len = max_seq_len;
+        self
+    }
+
+    /// Sets the maximum length of a single string or byte array. Exceeding this produces [`Error::BytesLengthLimit`](crate::Error::BytesLengthLimit).
+    pub fn with_max_bytes_len(mut self, max_bytes_len: usize) -> Self {
+        self.max_bytes_len = max_bytes_len;
+        self
+    }
+
+    /// Sets how deeply sequences, tuples, maps, structs and enums may be nested inside of each other. Exceeding this produces [`Error::DepthLimit`](crate::Error::DepthLimit).
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn header(&self) -> bool {
+        self.header
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn feature_flags(&self) -> FeatureFlags {
+        self.flags
+    }
+
+    pub fn high_precision(&self) -> bool {
+        self.flags.contains(FeatureFlags::HIGH_PRECISION)
+    }
+
+    pub fn typed(&self) -> bool {
+        self.flags.contains(FeatureFlags::TYPED)
+    }
+
+    pub fn skippable(&self) -> bool {
+        self.flags.contains(FeatureFlags::SKIPPABLE)
+    }
+
+    pub fn named_fields(&self) -> bool {
+        self.flags.contains(FeatureFlags::NAMED_FIELDS)
+    }
+
+    pub fn reject_trailing_bytes(&self) -> bool {
+        self.reject_trailing_bytes
+    }
+
+    pub fn canonical(&self) -> bool {
+        self.canonical
+    }
+
+    pub fn default_missing_fields(&self) -> bool {
+        self.default_missing_fields
+    }
+
+    pub fn max_alloc(&self) -> usize {
+        self.max_alloc
+    }
+
+    pub fn max_seq_len(&self) -> usize {
+        self.max_seq_len
+    }
+
+    pub fn max_bytes_len(&self) -> usize {
+        self.max_bytes_len
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Whether structs and enum variants are length prefixed. Typed data can already be skipped, so it doesn't need the length prefixes.
+    pub(crate) fn framed(&self) -> bool {
+        self.skippable() && !self.typed()
+    }
+
+    /// Whether struct fields are identified by the hash of their name. Typed data already stores the names themselves.
+    pub(crate) fn named(&self) -> bool {
+        self.named_fields() && !self.typed()
+    }
+
+    /// The header written for these options.
+    pub fn to_header(&self) -> Header {
+        Header::new(self.version, self.flags)
+    }
+
+    /// Takes the version and feature flags from a header that was read.
+    pub(crate) fn apply_header(mut self, header: Header) -> Self {
+        self.version = header.version;
+        self.flags = header.flags;
+        self
+    }
+}
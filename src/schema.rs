@@ -0,0 +1,1634 @@
+//! Runtime descriptions of how types are laid out in SBOF.
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{self, Display, Write};
+
+use serde::{
+    Deserialize,
+    de::{self, DeserializeSeed, IntoDeserializer, value::U32Deserializer},
+};
+
+use crate::{Error, Result, SbofOptions};
+
+/// The layout of a type in SBOF. Since SBOF isn't self-describing, this is needed to make sense of SBOF data without the type that wrote it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Char,
+    Str,
+    Bytes,
+    /// Any zero sized type, like `()` or a unit struct
+    Unit,
+    Option(Box<Schema>),
+    /// A variable amount of elements
+    Seq(Box<Schema>),
+    /// A constant amount of elements, like a tuple, array, or tuple struct
+    Tuple(Vec<Schema>),
+    Map {
+        key: Box<Schema>,
+        value: Box<Schema>,
+    },
+    Struct {
+        name: String,
+        fields: Vec<Field>,
+    },
+    /// An enumeration. The index of each variant is its position in `variants`.
+    Enum {
+        name: String,
+        variants: Vec<Variant>,
+    },
+}
+
+/// A named field of a struct or struct variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub schema: Schema,
+}
+
+/// A variant of an enum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub kind: VariantKind,
+}
+
+/// The data a variant holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantKind {
+    Unit,
+    Newtype(Schema),
+    Tuple(Vec<Schema>),
+    Struct(Vec<Field>),
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, schema: Schema) -> Self {
+        Field {
+            name: name.into(),
+            schema,
+        }
+    }
+}
+
+impl Variant {
+    pub fn new(name: impl Into<String>, kind: VariantKind) -> Self {
+        Variant {
+            name: name.into(),
+            kind,
+        }
+    }
+}
+
+impl Schema {
+    /// Traces the layout of `T` by driving its [`Deserialize`] implementation with placeholder values. Enums are traced once for each variant, so that every variant ends up in the schema.
+    ///
+    /// This fails with [`Error::Unsupported`] for types that need a self-describing format, like `#[serde(flatten)]` or `#[serde(untagged)]`, since SBOF can't store those either, and for recursive types, which a schema can't describe. Types are only known to be recursive once they nest too deeply or trace too many types, so very large types fail in the same way. Types that reject the placeholder values (zero, `false`, empty strings, and so on) can't be traced.
+    pub fn of<'de, T: Deserialize<'de>>() -> Result<Schema> {
+        let mut state = TraceState::default();
+        loop {
+            let mut schema = Schema::Unit;
+            T::deserialize(Tracer::new(&mut state, String::new(), &mut schema))?;
+            if state
+                .enums
+                .values()
+                .all(|e| e.kinds.iter().all(Option::is_some))
+            {
+                return Ok(state.fill(schema, ""));
+            }
+        }
+    }
+}
+
+/// Checks whether data written with the `old` version of a type can be read with the `new` version, and the other way around, with the default options.
+pub fn check_compat(old: &Schema, new: &Schema) -> Compat {
+    check_compat_options(old, new, &SbofOptions::new())
+}
+
+/// Checks whether data written with the `old` version of a type can be read with the `new` version, and the other way around, with specific [`SbofOptions`].
+///
+/// Without any feature flags, SBOF data only makes sense with the exact layout that wrote it, so most changes break compatibility. The changes that don't are:
+/// - Appending enum variants, for readers that know about them.
+/// - Appending struct fields, for readers that know about them, as long as they have `#[serde(default)]` and the reader enables [`SbofOptions::with_default_missing_fields`]. Without the "Skippable" feature flag, this only works for the structs at the end of the data.
+/// - Removing struct fields from the end, for readers that knew about them, with the "Skippable" feature flag. This counts as appending fields in the other direction.
+/// - Reordering, adding and removing struct fields anywhere, with the "Named Fields" or "Typed" feature flags.
+/// - Widening integers and floats, with the "Typed" feature flag.
+///
+/// Schemas don't record which fields have defaults, so fields that the data is missing are assumed to have them.
+pub fn check_compat_options(old: &Schema, new: &Schema, options: &SbofOptions) -> Compat {
+    let mut compat = Compat::default();
+    Checker::new(options, &mut compat.backward).check(old, new, String::new(), true);
+    Checker::new(options, &mut compat.forward).check(new, old, String::new(), true);
+    compat
+}
+
+/// The result of [`check_compat`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Compat {
+    /// What stops the new version from reading data written by the old version
+    pub backward: Vec<Breaking>,
+    /// What stops the old version from reading data written by the new version
+    pub forward: Vec<Breaking>,
+}
+
+impl Compat {
+    /// Whether the new version can read data written by the old version.
+    pub fn is_backward_compatible(&self) -> bool {
+        self.backward.is_empty()
+    }
+
+    /// Whether the old version can read data written by the new version.
+    pub fn is_forward_compatible(&self) -> bool {
+        self.forward.is_empty()
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        self.is_backward_compatible() && self.is_forward_compatible()
+    }
+}
+
+/// A change that stops one version of a type from reading data written by another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breaking {
+    /// The path to the changed value, like `.items[]::Named.distance`. The path is empty for the top-level value.
+    pub path: String,
+    pub reason: String,
+}
+
+impl Display for Breaking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "at `{}`: {}", self.path, self.reason)
+        }
+    }
+}
+
+/// Checks whether data written with one schema can be read with another.
+struct Checker<'a> {
+    /// Whether values that a reader doesn't know about can be skipped
+    skippable: bool,
+    /// Whether struct fields are matched by name instead of position
+    named: bool,
+    typed: bool,
+    default_missing_fields: bool,
+    out: &'a mut Vec<Breaking>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(options: &SbofOptions, out: &'a mut Vec<Breaking>) -> Self {
+        Checker {
+            skippable: options.skippable() || options.typed(),
+            named: options.named_fields() || options.typed(),
+            typed: options.typed(),
+            default_missing_fields: options.default_missing_fields(),
+            out,
+        }
+    }
+
+    fn breaking(&mut self, path: &str, reason: String) {
+        self.out.push(Breaking {
+            path: path.to_string(),
+            reason,
+        });
+    }
+
+    /// Checks whether a value written as `data` can be read as `reader`. `at_end` is whether nothing comes after the value in the data.
+    fn check(&mut self, data: &Schema, reader: &Schema, path: String, at_end: bool) {
+        match (data, reader) {
+            (Schema::Option(data), Schema::Option(reader)) => {
+                self.check(data, reader, format!("{path}?"), at_end)
+            }
+            (Schema::Seq(data), Schema::Seq(reader)) => {
+                self.check(data, reader, format!("{path}[]"), false)
+            }
+            (Schema::Tuple(data), Schema::Tuple(reader)) => {
+                if data.len() != reader.len() {
+                    let reason = format!(
+                        "{} elements were written, but {} are read",
+                        data.len(),
+                        reader.len()
+                    );
+                    return self.breaking(&path, reason);
+                }
+                self.check_elements(data, reader, &path, at_end);
+            }
+            (
+                Schema::Map { key, value },
+                Schema::Map {
+                    key: reader_key,
+                    value: reader_value,
+                },
+            ) => {
+                self.check(key, reader_key, format!("{path}{{key}}"), false);
+                self.check(value, reader_value, format!("{path}{{value}}"), false);
+            }
+            (Schema::Struct { fields, .. }, Schema::Struct { fields: reader, .. }) => {
+                self.check_fields(fields, reader, &path, at_end)
+            }
+            (
+                Schema::Enum { variants, .. },
+                Schema::Enum {
+                    variants: reader, ..
+                },
+            ) => self.check_variants(variants, reader, &path, at_end),
+            (data, reader) if data == reader => {}
+            (data, reader) if self.typed && widens(data, reader) => {}
+            (data, reader) => {
+                let reason = format!(
+                    "{} was written, but {} is read",
+                    describe(data),
+                    describe(reader)
+                );
+                self.breaking(&path, reason);
+            }
+        }
+    }
+
+    fn check_elements(&mut self, data: &[Schema], reader: &[Schema], path: &str, at_end: bool) {
+        for (index, (element, read)) in data.iter().zip(reader).enumerate() {
+            let last = index + 1 == data.len();
+            self.check(element, read, format!("{path}.{index}"), at_end && last);
+        }
+    }
+
+    fn check_fields(&mut self, data: &[Field], reader: &[Field], path: &str, at_end: bool) {
+        if self.named {
+            for field in data {
+                if let Some(read) = reader.iter().find(|f| f.name == field.name) {
+                    let path = format!("{path}.{}", field.name);
+                    self.check(&field.schema, &read.schema, path, false);
+                }
+            }
+            return;
+        }
+
+        for (index, (field, read)) in data.iter().zip(reader).enumerate() {
+            let path = format!("{path}.{}", read.name);
+            if field.name != read.name {
+                let reason = format!(
+                    "field {index} was written as `{}`, but is read as `{}`",
+                    field.name, read.name
+                );
+                self.breaking(&path, reason);
+            }
+            let last = index + 1 == data.len();
+            self.check(&field.schema, &read.schema, path, at_end && last);
+        }
+
+        if let Some(extra) = data.get(reader.len())
+            && !self.skippable
+        {
+            let reason = format!(
+                "field `{}` isn't read, and unknown fields can only be skipped with the \"Skippable\" feature flag",
+                extra.name
+            );
+            self.breaking(&format!("{path}.{}", extra.name), reason);
+        }
+        if let Some(missing) = reader.get(data.len()) {
+            let path = format!("{path}.{}", missing.name);
+            if !self.default_missing_fields {
+                let reason = format!(
+                    "field `{}` isn't written, and missing fields are only defaulted with `SbofOptions::with_default_missing_fields`",
+                    missing.name
+                );
+                self.breaking(&path, reason);
+            } else if !self.skippable && !at_end {
+                let reason = format!(
+                    "field `{}` isn't written, and without the \"Skippable\" feature flag, missing fields are only defaulted at the end of the data",
+                    missing.name
+                );
+                self.breaking(&path, reason);
+            }
+        }
+    }
+
+    fn check_variants(&mut self, data: &[Variant], reader: &[Variant], path: &str, at_end: bool) {
+        for (index, variant) in data.iter().enumerate() {
+            let path = format!("{path}::{}", variant.name);
+            let Some(read) = reader.get(index) else {
+                let reason = format!(
+                    "variant {index}, `{}`, isn't known by the reader",
+                    variant.name
+                );
+                self.breaking(&path, reason);
+                continue;
+            };
+            if variant.name != read.name {
+                let reason = format!(
+                    "variant {index} was written as `{}`, but is read as `{}`",
+                    variant.name, read.name
+                );
+                self.breaking(&path, reason);
+            }
+            match (&variant.kind, &read.kind) {
+                (VariantKind::Unit, VariantKind::Unit) => {}
+                (VariantKind::Newtype(data), VariantKind::Newtype(reader)) => {
+                    self.check(data, reader, path, at_end)
+                }
+                (VariantKind::Tuple(data), VariantKind::Tuple(reader))
+                    if data.len() == reader.len() =>
+                {
+                    self.check_elements(data, reader, &path, at_end)
+                }
+                (VariantKind::Struct(data), VariantKind::Struct(reader)) => {
+                    self.check_fields(data, reader, &path, at_end)
+                }
+                _ => {
+                    let reason = format!(
+                        "variant `{}` holds different data than the reader expects",
+                        variant.name
+                    );
+                    self.breaking(&path, reason);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a typed value written as `data` can always be read as `reader`.
+fn widens(data: &Schema, reader: &Schema) -> bool {
+    use Schema::*;
+
+    let unsigned = [U8, U16, U32, U64, U128];
+    let signed = [I8, I16, I32, I64, I128];
+    let position = |list: &[Schema], schema: &Schema| list.iter().position(|s| s == schema);
+    match (data, reader) {
+        (F32, F64) => true,
+        _ => match (position(&unsigned, data), position(&unsigned, reader)) {
+            (Some(data), Some(reader)) => data <= reader,
+            (Some(data), None) => position(&signed, reader).is_some_and(|reader| data < reader),
+            (None, _) => match (position(&signed, data), position(&signed, reader)) {
+                (Some(data), Some(reader)) => data <= reader,
+                _ => false,
+            },
+        },
+    }
+}
+
+/// A short description of a schema for error messages.
+fn describe(schema: &Schema) -> String {
+    match schema {
+        Schema::Option(_) => "an option".to_string(),
+        Schema::Seq(_) => "a sequence".to_string(),
+        Schema::Tuple(elements) => format!("a tuple of {}", elements.len()),
+        Schema::Map { .. } => "a map".to_string(),
+        Schema::Struct { name, .. } => format!("struct `{name}`"),
+        Schema::Enum { name, .. } => format!("enum `{name}`"),
+        schema => format!("{schema:?}"),
+    }
+}
+
+impl Schema {
+    /// Generates a JSON Schema (draft 2020-12) document describing the logical shape of values with this layout, in the form `serde_json` would represent them. Enums are externally tagged, so unit variants are strings, and other variants are objects with the variant's name as their only key.
+    pub fn to_json_schema(&self) -> String {
+        let mut root = match self.json() {
+            Json::Object(fields) => fields,
+            json => vec![("allOf".into(), Json::Array(vec![json]))],
+        };
+        root.insert(
+            0,
+            (
+                "$schema".into(),
+                Json::Str("https://json-schema.org/draft/2020-12/schema".into()),
+            ),
+        );
+        let mut out = String::new();
+        Json::Object(root).write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    /// Describes how values with this layout are stored in SBOF with `options`, one line per value, along with how many bytes each value takes. The "Typed" feature flag is ignored, since typed data describes itself.
+    ///
+    /// ```text
+    /// Player: struct `Player`, 3+ bytes. The fields, in order
+    ///   .name: str, 2+ bytes. UTF-8, prefixed by its length in bytes as an infinitely sized integer
+    ///   .level: u16, 1-3 bytes. Unsigned varint of up to 2 bytes
+    /// ```
+    pub fn layout(&self, options: &SbofOptions) -> String {
+        let mut out = String::new();
+        let label = match self {
+            Schema::Struct { name, .. } | Schema::Enum { name, .. } => name.clone(),
+            _ => "value".into(),
+        };
+        self.write_layout(&mut out, options, &label, 0);
+        out
+    }
+
+    /// The smallest and largest amount of bytes a value with this layout takes with `options`, ignoring the "Typed" feature flag. There is no largest size if the value can hold any amount of elements.
+    fn size(&self, options: &SbofOptions) -> (usize, Option<usize>) {
+        /// An infinitely sized integer, like a length prefix
+        const LEN: (usize, Option<usize>) = (2, Some(1 + size_of::<usize>()));
+        let varint = |bytes: usize| (1, Some(1 + bytes));
+        let fields = |fields: &[Field]| {
+            let size = if options.named() {
+                let id = varint(4);
+                let field = |f: &Field| sum_sizes([id, LEN, f.schema.size(options)].into_iter());
+                sum_sizes([LEN].into_iter().chain(fields.iter().map(field)))
+            } else {
+                sum_sizes(fields.iter().map(|f| f.schema.size(options)))
+            };
+            framed_size(size, options)
+        };
+
+        match self {
+            Schema::Bool | Schema::U8 | Schema::I8 => (1, Some(1)),
+            Schema::U16 | Schema::I16 => varint(2),
+            Schema::U32 | Schema::I32 | Schema::Char => varint(4),
+            Schema::U64 | Schema::I64 => varint(8),
+            Schema::U128 | Schema::I128 => varint(16),
+            Schema::F32 if options.high_precision() => (4, Some(4)),
+            Schema::F64 if options.high_precision() => (8, Some(8)),
+            Schema::F32 => (2, Some(6)),
+            Schema::F64 => (2, Some(12)),
+            Schema::Str | Schema::Bytes | Schema::Seq(_) | Schema::Map { .. } => (LEN.0, None),
+            Schema::Unit => (0, Some(0)),
+            Schema::Option(inner) => {
+                let (min, max) = inner.size(options);
+                (min.min(1), max.map(|max| max + 1))
+            }
+            Schema::Tuple(elements) => sum_sizes(elements.iter().map(|e| e.size(options))),
+            Schema::Struct { fields: f, .. } => fields(f),
+            Schema::Enum { variants, .. } => variants
+                .iter()
+                .map(|variant| {
+                    let data = match &variant.kind {
+                        VariantKind::Unit => (0, Some(0)),
+                        VariantKind::Newtype(inner) => inner.size(options),
+                        VariantKind::Tuple(elements) => {
+                            sum_sizes(elements.iter().map(|e| e.size(options)))
+                        }
+                        VariantKind::Struct(f) => fields(f),
+                    };
+                    sum_sizes([varint(4), framed_size(data, options)].into_iter())
+                })
+                .reduce(|(min, max), (v_min, v_max)| {
+                    (min.min(v_min), max.zip(v_max).map(|(a, b)| a.max(b)))
+                })
+                .unwrap_or((0, Some(0))),
+        }
+    }
+
+    fn write_layout(&self, out: &mut String, options: &SbofOptions, label: &str, depth: usize) {
+        let encoding = match self {
+            Schema::Bool => "00 for false, or 01 for true".to_string(),
+            Schema::U8 | Schema::I8 => "A single byte".to_string(),
+            Schema::U16 | Schema::U32 | Schema::U64 | Schema::U128 => {
+                format!("Unsigned varint of up to {} bytes", self.int_bytes())
+            }
+            Schema::I16 | Schema::I32 | Schema::I64 | Schema::I128 => {
+                format!("Signed varint of up to {} bytes", self.int_bytes())
+            }
+            Schema::F32 if options.high_precision() => {
+                "IEEE 754 single precision float".to_string()
+            }
+            Schema::F64 if options.high_precision() => {
+                "IEEE 754 double precision float".to_string()
+            }
+            Schema::F32 => "Transformed float: the significand as a signed varint of up to 4 bytes, then the exponent as a single byte".to_string(),
+            Schema::F64 => "Transformed float: the significand as a signed varint of up to 8 bytes, then the exponent as a signed varint of up to 2 bytes".to_string(),
+            Schema::Char => "UTF-32 code point, as an unsigned varint of up to 4 bytes".to_string(),
+            Schema::Str => {
+                "UTF-8, prefixed by its length in bytes as an infinitely sized integer".to_string()
+            }
+            Schema::Bytes => {
+                "Raw bytes, prefixed by their length as an infinitely sized integer".to_string()
+            }
+            Schema::Unit => "Nothing".to_string(),
+            Schema::Option(_) => "00 for none, or the value, prefixed by 01 if the value starts with 00 or 01".to_string(),
+            Schema::Seq(_) => "The elements, prefixed by how many there are as an infinitely sized integer".to_string(),
+            Schema::Map { .. } => "The keys and values, prefixed by how many entries there are as an infinitely sized integer".to_string(),
+            Schema::Tuple(_) => "The elements, in order".to_string(),
+            Schema::Struct { .. } => fields_layout(options),
+            Schema::Enum { .. } => {
+                let mut encoding =
+                    "The variant index as an unsigned varint of up to 4 bytes, then the variant's data"
+                        .to_string();
+                if options.framed() {
+                    encoding.push_str(", prefixed by its length in bytes as an infinitely sized integer");
+                }
+                encoding
+            }
+        };
+        let name = match self {
+            Schema::Option(_)
+            | Schema::Seq(_)
+            | Schema::Tuple(_)
+            | Schema::Map { .. }
+            | Schema::Struct { .. }
+            | Schema::Enum { .. } => describe(self),
+            primitive => format!("{primitive:?}").to_lowercase(),
+        };
+        writeln!(
+            out,
+            "{}{label}: {name}, {}. {encoding}",
+            "  ".repeat(depth),
+            format_size(self.size(options))
+        )
+        .unwrap();
+
+        let depth = depth + 1;
+        match self {
+            Schema::Option(inner) => inner.write_layout(out, options, "?", depth),
+            Schema::Seq(element) => element.write_layout(out, options, "[]", depth),
+            Schema::Map { key, value } => {
+                key.write_layout(out, options, "{key}", depth);
+                value.write_layout(out, options, "{value}", depth);
+            }
+            Schema::Tuple(elements) => write_elements_layout(out, options, elements, depth),
+            Schema::Struct { fields, .. } => write_fields_layout(out, options, fields, depth),
+            Schema::Enum { variants, .. } => {
+                for variant in variants {
+                    let label = format!("::{}", variant.name);
+                    match &variant.kind {
+                        VariantKind::Unit => {
+                            writeln!(out, "{}{label}: unit, 0 bytes. Nothing", "  ".repeat(depth))
+                                .unwrap()
+                        }
+                        VariantKind::Newtype(inner) => {
+                            inner.write_layout(out, options, &label, depth)
+                        }
+                        VariantKind::Tuple(elements) => {
+                            let size = sum_sizes(elements.iter().map(|e| e.size(options)));
+                            writeln!(
+                                out,
+                                "{}{label}: tuple variant, {}. The elements, in order",
+                                "  ".repeat(depth),
+                                format_size(size)
+                            )
+                            .unwrap();
+                            write_elements_layout(out, options, elements, depth + 1);
+                        }
+                        VariantKind::Struct(fields) => {
+                            let schema = Schema::Struct {
+                                name: String::new(),
+                                fields: fields.clone(),
+                            };
+                            writeln!(
+                                out,
+                                "{}{label}: struct variant, {}. {}",
+                                "  ".repeat(depth),
+                                format_size(schema.size(options)),
+                                fields_layout(options)
+                            )
+                            .unwrap();
+                            write_fields_layout(out, options, fields, depth + 1);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The most bytes an integer type stores its value in.
+    fn int_bytes(&self) -> usize {
+        match self {
+            Schema::U16 | Schema::I16 => 2,
+            Schema::U32 | Schema::I32 => 4,
+            Schema::U64 | Schema::I64 => 8,
+            Schema::U128 | Schema::I128 => 16,
+            _ => 1,
+        }
+    }
+
+    fn json(&self) -> Json {
+        let object = |fields: Vec<(&str, Json)>| {
+            Json::Object(
+                fields
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            )
+        };
+        let ty = |ty: &str| ("type", Json::Str(ty.into()));
+        let int = |min: String, max: String| {
+            object(vec![
+                ty("integer"),
+                ("minimum", Json::Number(min)),
+                ("maximum", Json::Number(max)),
+            ])
+        };
+        let elements = |elements: &[Schema]| {
+            let len = Json::Number(elements.len().to_string());
+            object(vec![
+                ty("array"),
+                (
+                    "prefixItems",
+                    Json::Array(elements.iter().map(Schema::json).collect()),
+                ),
+                ("minItems", len.clone()),
+                ("maxItems", len),
+            ])
+        };
+        let fields = |name: Option<&str>, fields: &[Field]| {
+            let mut json = Vec::new();
+            if let Some(name) = name {
+                json.push(("title", Json::Str(name.into())));
+            }
+            json.extend([
+                ty("object"),
+                (
+                    "properties",
+                    Json::Object(
+                        fields
+                            .iter()
+                            .map(|f| (f.name.clone(), f.schema.json()))
+                            .collect(),
+                    ),
+                ),
+                (
+                    "required",
+                    Json::Array(fields.iter().map(|f| Json::Str(f.name.clone())).collect()),
+                ),
+                ("additionalProperties", Json::Bool(false)),
+            ]);
+            object(json)
+        };
+
+        match self {
+            Schema::Bool => object(vec![ty("boolean")]),
+            Schema::U8 => int(u8::MIN.to_string(), u8::MAX.to_string()),
+            Schema::U16 => int(u16::MIN.to_string(), u16::MAX.to_string()),
+            Schema::U32 => int(u32::MIN.to_string(), u32::MAX.to_string()),
+            Schema::U64 => int(u64::MIN.to_string(), u64::MAX.to_string()),
+            Schema::U128 => int(u128::MIN.to_string(), u128::MAX.to_string()),
+            Schema::I8 => int(i8::MIN.to_string(), i8::MAX.to_string()),
+            Schema::I16 => int(i16::MIN.to_string(), i16::MAX.to_string()),
+            Schema::I32 => int(i32::MIN.to_string(), i32::MAX.to_string()),
+            Schema::I64 => int(i64::MIN.to_string(), i64::MAX.to_string()),
+            Schema::I128 => int(i128::MIN.to_string(), i128::MAX.to_string()),
+            Schema::F32 | Schema::F64 => object(vec![ty("number")]),
+            Schema::Char => object(vec![
+                ty("string"),
+                ("minLength", Json::Number("1".into())),
+                ("maxLength", Json::Number("1".into())),
+            ]),
+            Schema::Str => object(vec![ty("string")]),
+            Schema::Bytes => object(vec![ty("array"), ("items", Schema::U8.json())]),
+            Schema::Unit => object(vec![ty("null")]),
+            Schema::Option(inner) => object(vec![(
+                "anyOf",
+                Json::Array(vec![inner.json(), object(vec![ty("null")])]),
+            )]),
+            Schema::Seq(element) => object(vec![ty("array"), ("items", element.json())]),
+            Schema::Tuple(e) => elements(e),
+            Schema::Map { value, .. } => {
+                object(vec![ty("object"), ("additionalProperties", value.json())])
+            }
+            Schema::Struct { name, fields: f } => fields(Some(name), f),
+            Schema::Enum { name, variants } => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let data = match &variant.kind {
+                            VariantKind::Unit => {
+                                return object(vec![("const", Json::Str(variant.name.clone()))]);
+                            }
+                            VariantKind::Newtype(inner) => inner.json(),
+                            VariantKind::Tuple(e) => elements(e),
+                            VariantKind::Struct(f) => fields(None, f),
+                        };
+                        object(vec![
+                            ty("object"),
+                            (
+                                "properties",
+                                Json::Object(vec![(variant.name.clone(), data)]),
+                            ),
+                            (
+                                "required",
+                                Json::Array(vec![Json::Str(variant.name.clone())]),
+                            ),
+                            ("additionalProperties", Json::Bool(false)),
+                        ])
+                    })
+                    .collect();
+                object(vec![
+                    ("title", Json::Str(name.clone())),
+                    ("oneOf", Json::Array(variants)),
+                ])
+            }
+        }
+    }
+}
+
+/// Adds the length prefix that structs and the data of enum variants have with the "Skippable" feature flag.
+fn framed_size(size: (usize, Option<usize>), options: &SbofOptions) -> (usize, Option<usize>) {
+    if options.framed() {
+        let (min, max) = size;
+        (min + 2, max.map(|max| max + 1 + size_of::<usize>()))
+    } else {
+        size
+    }
+}
+
+fn sum_sizes(sizes: impl Iterator<Item = (usize, Option<usize>)>) -> (usize, Option<usize>) {
+    sizes.fold((0, Some(0)), |(min, max), (size_min, size_max)| {
+        (min + size_min, max.zip(size_max).map(|(a, b)| a + b))
+    })
+}
+
+fn format_size((min, max): (usize, Option<usize>)) -> String {
+    match max {
+        Some(1) if min == 1 => "1 byte".to_string(),
+        Some(max) if max == min => format!("{min} bytes"),
+        Some(max) => format!("{min}-{max} bytes"),
+        None => format!("{min}+ bytes"),
+    }
+}
+
+fn fields_layout(options: &SbofOptions) -> String {
+    let mut encoding = if options.named() {
+        "The fields in any order, prefixed by how many there are as an infinitely sized integer. Each field is its name's FNV-1a hash as an unsigned varint of up to 4 bytes, then its length in bytes as an infinitely sized integer, then its value"
+    } else {
+        "The fields, in order"
+    }
+    .to_string();
+    if options.framed() {
+        encoding.push_str(", prefixed by their length in bytes as an infinitely sized integer");
+    }
+    encoding
+}
+
+fn write_elements_layout(
+    out: &mut String,
+    options: &SbofOptions,
+    elements: &[Schema],
+    depth: usize,
+) {
+    for (index, element) in elements.iter().enumerate() {
+        element.write_layout(out, options, &format!(".{index}"), depth);
+    }
+}
+
+fn write_fields_layout(out: &mut String, options: &SbofOptions, fields: &[Field], depth: usize) {
+    for field in fields {
+        field
+            .schema
+            .write_layout(out, options, &format!(".{}", field.name), depth);
+    }
+}
+
+/// A JSON value, for writing JSON Schema documents without depending on a JSON library.
+#[derive(Clone)]
+enum Json {
+    Bool(bool),
+    /// A number, already formatted
+    Number(String),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Writes the value with two spaces of indentation per level.
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth + 1);
+        match self {
+            Json::Bool(value) => write!(out, "{value}").unwrap(),
+            Json::Number(value) => out.push_str(value),
+            Json::Str(value) => write_json_str(out, value),
+            Json::Array(values) if values.is_empty() => out.push_str("[]"),
+            Json::Array(values) => {
+                out.push_str("[\n");
+                for (i, value) in values.iter().enumerate() {
+                    out.push_str(&indent);
+                    value.write(out, depth + 1);
+                    out.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                write!(out, "{}]", "  ".repeat(depth)).unwrap();
+            }
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Object(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&indent);
+                    write_json_str(out, key);
+                    out.push_str(": ");
+                    value.write(out, depth + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                write!(out, "{}}}", "  ".repeat(depth)).unwrap();
+            }
+        }
+    }
+}
+
+fn write_json_str(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How many times a type with the same name can be nested inside of itself before it's treated as recursive.
+const MAX_NESTING: usize = 32;
+
+/// How many named types can be traced over every tracing run, so that recursive enums with several recursive fields, which get deeper with each run, fail quickly.
+const MAX_TRACED: usize = 1 << 14;
+
+/// What has been learned about the enums in a type over the tracing runs so far.
+#[derive(Default)]
+struct TraceState {
+    /// Enums by the path to them, since the same path always leads to the same type
+    enums: BTreeMap<String, TracedEnum>,
+    /// Names of the types being traced, to detect recursive types. Names alone can't tell a recursive type from nested types that share a name, like `Wrapper<Wrapper<u8>>`, so types are only treated as recursive once a name repeats more than [`MAX_NESTING`] times.
+    stack: Vec<&'static str>,
+    /// How many named types have been traced over every run so far
+    traced: usize,
+}
+
+struct TracedEnum {
+    variants: &'static [&'static str],
+    kinds: Vec<Option<VariantKind>>,
+}
+
+impl TraceState {
+    /// Whether any enum below `prefix` still has variants that weren't traced.
+    fn incomplete(&self, prefix: &str) -> bool {
+        self.enums.iter().any(|(path, e)| {
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_digit()))
+                && e.kinds.iter().any(Option::is_none)
+        })
+    }
+
+    /// Replaces the enums in `schema`, which only stand in for the enums at their paths, with every variant that was traced.
+    fn fill(&self, schema: Schema, path: &str) -> Schema {
+        match schema {
+            Schema::Option(inner) => {
+                Schema::Option(Box::new(self.fill(*inner, &format!("{path}?"))))
+            }
+            Schema::Seq(element) => {
+                Schema::Seq(Box::new(self.fill(*element, &format!("{path}[]"))))
+            }
+            Schema::Tuple(elements) => Schema::Tuple(self.fill_tuple(elements, path)),
+            Schema::Map { key, value } => Schema::Map {
+                key: Box::new(self.fill(*key, &format!("{path}{{key}}"))),
+                value: Box::new(self.fill(*value, &format!("{path}{{value}}"))),
+            },
+            Schema::Struct { name, fields } => Schema::Struct {
+                name,
+                fields: self.fill_fields(fields, path),
+            },
+            Schema::Enum { name, .. } => {
+                let traced = &self.enums[path];
+                let variants = traced
+                    .variants
+                    .iter()
+                    .zip(&traced.kinds)
+                    .enumerate()
+                    .map(|(index, (&variant, kind))| {
+                        let path = format!("{path}::{index}");
+                        let kind = match kind.clone().expect("every variant was traced") {
+                            VariantKind::Unit => VariantKind::Unit,
+                            VariantKind::Newtype(inner) => {
+                                VariantKind::Newtype(self.fill(inner, &path))
+                            }
+                            VariantKind::Tuple(elements) => {
+                                VariantKind::Tuple(self.fill_tuple(elements, &path))
+                            }
+                            VariantKind::Struct(fields) => {
+                                VariantKind::Struct(self.fill_fields(fields, &path))
+                            }
+                        };
+                        Variant::new(variant, kind)
+                    })
+                    .collect();
+                Schema::Enum { name, variants }
+            }
+            schema => schema,
+        }
+    }
+
+    fn fill_tuple(&self, elements: Vec<Schema>, path: &str) -> Vec<Schema> {
+        elements
+            .into_iter()
+            .enumerate()
+            .map(|(index, element)| self.fill(element, &format!("{path}.{index}")))
+            .collect()
+    }
+
+    fn fill_fields(&self, fields: Vec<Field>, path: &str) -> Vec<Field> {
+        fields
+            .into_iter()
+            .map(|field| {
+                let schema = self.fill(field.schema, &format!("{path}.{}", field.name));
+                Field::new(field.name, schema)
+            })
+            .collect()
+    }
+}
+
+/// A [`de::Deserializer`] that records the layout of whatever is deserialized from it into `out`, and hands out placeholder values.
+struct Tracer<'a> {
+    state: &'a mut TraceState,
+    path: String,
+    out: &'a mut Schema,
+}
+
+impl<'a> Tracer<'a> {
+    fn new(state: &'a mut TraceState, path: String, out: &'a mut Schema) -> Self {
+        Tracer { state, path, out }
+    }
+
+    /// Runs `f` inside of the type called `name`, failing if the type seems to contain itself.
+    fn within<T>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.state.traced += 1;
+        if self.state.traced > MAX_TRACED
+            || self.state.stack.iter().filter(|&&n| n == name).count() >= MAX_NESTING
+        {
+            return Err(Error::Unsupported {
+                name,
+                reason: "recursive types can't be described by a schema",
+            });
+        }
+        self.state.stack.push(name);
+        let res = f(self);
+        self.state.stack.pop();
+        res
+    }
+}
+
+/// Traces `seed` at `path`, returning its schema along with the value.
+fn trace<'de, T: DeserializeSeed<'de>>(
+    state: &mut TraceState,
+    path: String,
+    seed: T,
+) -> Result<(T::Value, Schema)> {
+    let mut schema = Schema::Unit;
+    let value = seed.deserialize(Tracer::new(state, path, &mut schema))?;
+    Ok((value, schema))
+}
+
+macro_rules! trace_primitive {
+    ($($method:ident => $visit:ident($($value:expr)?), $schema:ident;)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                *self.out = Schema::$schema;
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Tracer<'_> {
+    type Error = Error;
+
+    trace_primitive! {
+        deserialize_bool => visit_bool(false), Bool;
+        deserialize_u8 => visit_u8(0), U8;
+        deserialize_u16 => visit_u16(0), U16;
+        deserialize_u32 => visit_u32(0), U32;
+        deserialize_u64 => visit_u64(0), U64;
+        deserialize_u128 => visit_u128(0), U128;
+        deserialize_i8 => visit_i8(0), I8;
+        deserialize_i16 => visit_i16(0), I16;
+        deserialize_i32 => visit_i32(0), I32;
+        deserialize_i64 => visit_i64(0), I64;
+        deserialize_i128 => visit_i128(0), I128;
+        deserialize_f32 => visit_f32(0.0), F32;
+        deserialize_f64 => visit_f64(0.0), F64;
+        deserialize_char => visit_char('\0'), Char;
+        deserialize_str => visit_borrowed_str(""), Str;
+        deserialize_string => visit_borrowed_str(""), Str;
+        deserialize_bytes => visit_borrowed_bytes(&[]), Bytes;
+        deserialize_byte_buf => visit_borrowed_bytes(&[]), Bytes;
+        deserialize_unit => visit_unit(), Unit;
+    }
+
+    fn deserialize_any<V>(self, _: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::Unsupported {
+            name: "deserialize_any",
+            reason: "SBOF is not a self-describing format",
+        })
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut inner = Schema::Unit;
+        let value = visitor.visit_some(Tracer::new(self.state, self.path + "?", &mut inner))?;
+        *self.out = Schema::Option(Box::new(inner));
+        Ok(value)
+    }
+
+    fn deserialize_unit_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        *self.out = Schema::Unit;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(mut self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        // Newtype structs are written as the value they hold
+        self.within(name, |tracer| {
+            let tracer = Tracer::new(tracer.state, tracer.path.clone(), tracer.out);
+            visitor.visit_newtype_struct(tracer)
+        })
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut seq = TraceSeq::new(self.state, self.path, 1, true);
+        let value = visitor.visit_seq(&mut seq)?;
+        let element = seq.schemas.pop().unwrap_or(Schema::Unit);
+        *self.out = Schema::Seq(Box::new(element));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut seq = TraceSeq::new(self.state, self.path, len, false);
+        let value = visitor.visit_seq(&mut seq)?;
+        *self.out = Schema::Tuple(seq.schemas);
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        mut self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.within(name, |tracer| {
+            let tracer = Tracer::new(tracer.state, tracer.path.clone(), tracer.out);
+            de::Deserializer::deserialize_tuple(tracer, len, visitor)
+        })
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut map = TraceMap {
+            state: self.state,
+            path: self.path,
+            done: false,
+            key: Schema::Unit,
+            value: Schema::Unit,
+        };
+        let value = visitor.visit_map(&mut map)?;
+        *self.out = Schema::Map {
+            key: Box::new(map.key),
+            value: Box::new(map.value),
+        };
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.within(name, |tracer| {
+            let mut access = TraceStruct::new(tracer.state, tracer.path.clone(), fields);
+            let value = visitor.visit_map(&mut access)?;
+            *tracer.out = Schema::Struct {
+                name: name.into(),
+                fields: access.traced,
+            };
+            Ok(value)
+        })
+    }
+
+    fn deserialize_enum<V>(
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.within(name, |tracer| {
+            let state = &mut *tracer.state;
+            let path = tracer.path.clone();
+            let traced = state.enums.entry(path.clone()).or_insert(TracedEnum {
+                variants,
+                kinds: vec![None; variants.len()],
+            });
+            // Trace variants that haven't been traced yet, then variants that contain enums that haven't been fully traced yet
+            let untraced = traced.kinds.iter().position(Option::is_none);
+            let index = untraced
+                .or_else(|| (0..variants.len()).find(|i| state.incomplete(&format!("{path}::{i}"))))
+                .unwrap_or(0);
+
+            let mut access = TraceEnum {
+                state,
+                path: format!("{path}::{index}"),
+                index: index as u32,
+                kind: None,
+            };
+            let value = visitor.visit_enum(&mut access)?;
+            if let Some(kind) = access.kind {
+                state.enums.get_mut(&path).expect("enum was inserted").kinds[index] = Some(kind);
+            }
+            // The variants are filled in once every one of them has been traced
+            *tracer.out = Schema::Enum {
+                name: name.into(),
+                variants: Vec::new(),
+            };
+            Ok(value)
+        })
+    }
+
+    fn deserialize_identifier<V>(self, _: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::Unsupported {
+            name: "deserialize_identifier",
+            reason: "SBOF is not a self-describing format",
+        })
+    }
+
+    fn deserialize_ignored_any<V>(self, _: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::Unsupported {
+            name: "deserialize_ignored_any",
+            reason: "SBOF is not a self-describing format",
+        })
+    }
+}
+
+/// Traces the elements of a sequence or tuple. Sequences are traced with a single element.
+struct TraceSeq<'a> {
+    state: &'a mut TraceState,
+    path: String,
+    len: usize,
+    seq: bool,
+    schemas: Vec<Schema>,
+}
+
+impl<'a> TraceSeq<'a> {
+    fn new(state: &'a mut TraceState, path: String, len: usize, seq: bool) -> Self {
+        TraceSeq {
+            state,
+            path,
+            len,
+            seq,
+            schemas: Vec::new(),
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for TraceSeq<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let index = self.schemas.len();
+        if index >= self.len {
+            return Ok(None);
+        }
+        let path = if self.seq {
+            format!("{}[]", self.path)
+        } else {
+            format!("{}.{index}", self.path)
+        };
+        let (value, schema) = trace(self.state, path, seed)?;
+        self.schemas.push(schema);
+        Ok(Some(value))
+    }
+}
+
+/// Traces a map with a single entry.
+struct TraceMap<'a> {
+    state: &'a mut TraceState,
+    path: String,
+    done: bool,
+    key: Schema,
+    value: Schema,
+}
+
+impl<'de> de::MapAccess<'de> for TraceMap<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.done {
+            return Ok(None);
+        }
+        let (key, schema) = trace(self.state, format!("{}{{key}}", self.path), seed)?;
+        self.key = schema;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.done = true;
+        let (value, schema) = trace(self.state, format!("{}{{value}}", self.path), seed)?;
+        self.value = schema;
+        Ok(value)
+    }
+}
+
+/// Traces the fields of a struct or struct variant, in the same order that the deserializer reads them.
+struct TraceStruct<'a> {
+    state: &'a mut TraceState,
+    path: String,
+    fields: &'static [&'static str],
+    traced: Vec<Field>,
+}
+
+impl<'a> TraceStruct<'a> {
+    fn new(state: &'a mut TraceState, path: String, fields: &'static [&'static str]) -> Self {
+        TraceStruct {
+            state,
+            path,
+            fields,
+            traced: Vec::new(),
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for TraceStruct<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.fields.get(self.traced.len()) {
+            Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let field = self.fields[self.traced.len()];
+        let (value, schema) = trace(self.state, format!("{}.{field}", self.path), seed)?;
+        self.traced.push(Field::new(field, schema));
+        Ok(value)
+    }
+}
+
+/// Traces the variant of an enum that was chosen by [`Tracer::deserialize_enum`].
+struct TraceEnum<'a> {
+    state: &'a mut TraceState,
+    path: String,
+    index: u32,
+    kind: Option<VariantKind>,
+}
+
+impl<'de> de::EnumAccess<'de> for &mut TraceEnum<'_> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize::<U32Deserializer<Error>>(self.index.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut TraceEnum<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.kind = Some(VariantKind::Unit);
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let (value, schema) = trace(self.state, self.path.clone(), seed)?;
+        self.kind = Some(VariantKind::Newtype(schema));
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut seq = TraceSeq::new(self.state, self.path.clone(), len, false);
+        let value = visitor.visit_seq(&mut seq)?;
+        self.kind = Some(VariantKind::Tuple(seq.schemas));
+        Ok(value)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut access = TraceStruct::new(self.state, self.path.clone(), fields);
+        let value = visitor.visit_map(&mut access)?;
+        self.kind = Some(VariantKind::Struct(access.traced));
+        Ok(value)
+    }
+}
+
+#[test]
+fn trace_test() -> Result<()> {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    struct Meters(f64);
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    enum Inner {
+        A,
+        B(u8),
+    }
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    enum Outer {
+        Empty,
+        Nested(Inner, Option<Inner>),
+        Named { distance: Meters },
+    }
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    struct Root {
+        name: String,
+        items: Vec<Outer>,
+        lookup: std::collections::HashMap<u16, (bool, char)>,
+    }
+
+    let inner = Schema::Enum {
+        name: "Inner".into(),
+        variants: vec![
+            Variant::new("A", VariantKind::Unit),
+            Variant::new("B", VariantKind::Newtype(Schema::U8)),
+        ],
+    };
+    let outer = Schema::Enum {
+        name: "Outer".into(),
+        variants: vec![
+            Variant::new("Empty", VariantKind::Unit),
+            Variant::new(
+                "Nested",
+                VariantKind::Tuple(vec![inner.clone(), Schema::Option(Box::new(inner))]),
+            ),
+            Variant::new(
+                "Named",
+                VariantKind::Struct(vec![Field::new("distance", Schema::F64)]),
+            ),
+        ],
+    };
+    let expected = Schema::Struct {
+        name: "Root".into(),
+        fields: vec![
+            Field::new("name", Schema::Str),
+            Field::new("items", Schema::Seq(Box::new(outer))),
+            Field::new(
+                "lookup",
+                Schema::Map {
+                    key: Box::new(Schema::U16),
+                    value: Box::new(Schema::Tuple(vec![Schema::Bool, Schema::Char])),
+                },
+            ),
+        ],
+    };
+    assert_eq!(Schema::of::<Root>()?, expected);
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    struct List {
+        next: Option<Box<List>>,
+    }
+    assert!(matches!(
+        Schema::of::<List>(),
+        Err(Error::Unsupported { name: "List", .. })
+    ));
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    enum Tree {
+        Leaf,
+        Node(Box<Tree>, Box<Tree>),
+    }
+    assert!(matches!(
+        Schema::of::<Tree>(),
+        Err(Error::Unsupported { name: "Tree", .. })
+    ));
+
+    // Nested types that share a name aren't recursive
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    struct Wrapper<T> {
+        inner: T,
+    }
+    let wrapper = |inner| Schema::Struct {
+        name: "Wrapper".into(),
+        fields: vec![Field::new("inner", inner)],
+    };
+    assert_eq!(
+        Schema::of::<Wrapper<Wrapper<u8>>>()?,
+        wrapper(wrapper(Schema::U8))
+    );
+
+    #[derive(Deserialize)]
+    #[allow(unused)]
+    struct Id(u8);
+    #[derive(Deserialize)]
+    #[serde(rename = "Id")]
+    #[allow(unused)]
+    struct OuterId(Id, Option<Id>);
+    assert_eq!(
+        Schema::of::<OuterId>()?,
+        Schema::Tuple(vec![Schema::U8, Schema::Option(Box::new(Schema::U8))])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn compat_test() {
+    let v1 = Schema::Struct {
+        name: "Player".into(),
+        fields: vec![
+            Field::new("name", Schema::Str),
+            Field::new("level", Schema::U16),
+            Field::new(
+                "class",
+                Schema::Enum {
+                    name: "Class".into(),
+                    variants: vec![Variant::new("Knight", VariantKind::Unit)],
+                },
+            ),
+        ],
+    };
+    let mut v2 = v1.clone();
+    if let Schema::Struct { fields, .. } = &mut v2 {
+        fields.push(Field::new("guild", Schema::Option(Box::new(Schema::Str))));
+        if let Schema::Enum { variants, .. } = &mut fields[2].schema {
+            variants.push(Variant::new("Mage", VariantKind::Newtype(Schema::U8)));
+        }
+    }
+
+    // Fields added at the end can only be defaulted at the end of the data
+    let compat = check_compat(&v1, &v2);
+    assert_eq!(
+        compat.backward,
+        [Breaking {
+            path: ".guild".into(),
+            reason: "field `guild` isn't written, and missing fields are only defaulted with `SbofOptions::with_default_missing_fields`".into(),
+        }]
+    );
+    assert_eq!(compat.forward.len(), 2);
+    assert_eq!(compat.forward[0].path, ".class::Mage");
+    assert_eq!(compat.forward[1].path, ".guild");
+
+    let options = SbofOptions::new().with_default_missing_fields(true);
+    assert!(check_compat_options(&v1, &v2, &options).is_backward_compatible());
+    let list = |s: &Schema| Schema::Seq(Box::new(s.clone()));
+    let compat = check_compat_options(&list(&v1), &list(&v2), &options);
+    assert_eq!(compat.backward[0].path, "[].guild");
+
+    let options = options.with_skippable(true);
+    let compat = check_compat_options(&list(&v1), &list(&v2), &options);
+    assert!(compat.is_backward_compatible());
+    assert_eq!(compat.forward.len(), 1);
+
+    // Integers are only widened with the "Typed" feature flag, since small values are stored differently
+    let wide = Schema::Tuple(vec![Schema::U32, Schema::I64]);
+    let narrow = Schema::Tuple(vec![Schema::U16, Schema::U8]);
+    let compat = check_compat(&narrow, &wide);
+    assert_eq!(compat.backward.len(), 2);
+    assert_eq!(
+        compat.backward[0].to_string(),
+        "at `.0`: U16 was written, but U32 is read"
+    );
+    let compat = check_compat_options(&narrow, &wide, &SbofOptions::new().with_typed(true));
+    assert!(compat.is_backward_compatible());
+    assert_eq!(compat.forward.len(), 2);
+
+    // Fields can be reordered with the "Named Fields" feature flag
+    let a = Schema::Struct {
+        name: "A".into(),
+        fields: vec![Field::new("x", Schema::U8), Field::new("y", Schema::U8)],
+    };
+    let b = Schema::Struct {
+        name: "A".into(),
+        fields: vec![Field::new("y", Schema::U8), Field::new("x", Schema::U8)],
+    };
+    assert_eq!(check_compat(&a, &b).backward.len(), 2);
+    let options = SbofOptions::new().with_named_fields(true);
+    assert!(check_compat_options(&a, &b, &options).is_compatible());
+}
+
+#[test]
+fn doc_test() {
+    let schema = Schema::Struct {
+        name: "Player".into(),
+        fields: vec![
+            Field::new("name", Schema::Str),
+            Field::new("speed", Schema::F32),
+            Field::new(
+                "class",
+                Schema::Enum {
+                    name: "Class".into(),
+                    variants: vec![
+                        Variant::new("Knight", VariantKind::Unit),
+                        Variant::new("Mage", VariantKind::Tuple(vec![Schema::U8, Schema::U16])),
+                    ],
+                },
+            ),
+        ],
+    };
+
+    assert_eq!(
+        schema.layout(&SbofOptions::new()),
+        "\
+Player: struct `Player`, 5+ bytes. The fields, in order
+  .name: str, 2+ bytes. UTF-8, prefixed by its length in bytes as an infinitely sized integer
+  .speed: f32, 2-6 bytes. Transformed float: the significand as a signed varint of up to 4 bytes, then the exponent as a single byte
+  .class: enum `Class`, 1-9 bytes. The variant index as an unsigned varint of up to 4 bytes, then the variant's data
+    ::Knight: unit, 0 bytes. Nothing
+    ::Mage: tuple variant, 2-4 bytes. The elements, in order
+      .0: u8, 1 byte. A single byte
+      .1: u16, 1-3 bytes. Unsigned varint of up to 2 bytes
+"
+    );
+    let layout = schema.layout(&SbofOptions::new().with_high_precision(true));
+    assert!(layout.contains("  .speed: f32, 4 bytes. IEEE 754 single precision float\n"));
+
+    let json: serde_json::Value = serde_json::from_str(&schema.to_json_schema()).unwrap();
+    assert_eq!(json["title"], "Player");
+    assert_eq!(
+        json["required"],
+        serde_json::json!(["name", "speed", "class"])
+    );
+    let class = &json["properties"]["class"]["oneOf"];
+    assert_eq!(class[0]["const"], "Knight");
+    assert_eq!(class[1]["properties"]["Mage"]["maxItems"], 2);
+    assert_eq!(
+        class[1]["properties"]["Mage"]["prefixItems"][1]["maximum"],
+        u16::MAX
+    );
+}
@@ -1,79 +1,216 @@
+use alloc::{format, vec::Vec};
+#[cfg(feature = "std")]
 use std::io::Write;
 
-use crate::{Error, Result, sign_extend_le};
+use crate::{Error, Result, SbofOptions, sign_extend_le, tag::Tag};
 
 use serde::{Serialize, ser};
 
 /// Converts a value that implements [`Serialize`] into a Vec<u8> of SBOF data. By default, there is a header, and the "High Precision Floats" feature is disabled.
 pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
-    to_bytes_settings(value, true, false)
+    to_bytes_options(value, &SbofOptions::new())
 }
 
 // Used for testing, obviously
 #[allow(unused)]
 fn to_bytes_testing<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
-    to_bytes_settings(value, false, false)
+    to_bytes_options(value, &SbofOptions::new().with_header(false))
 }
 
 /// Converts a value that implements [`Serialize`] into a Vec<u8> of SBOF data, with specific settings.
+#[deprecated(note = "use `to_bytes_options` instead")]
 pub fn to_bytes_settings<T: Serialize + ?Sized>(
     value: &T,
     header: bool,
     high_precision: bool,
 ) -> Result<Vec<u8>> {
-    let mut serializer = if header {
-        generate_header(high_precision)
-    } else {
-        Serializer::new(Vec::new(), high_precision)
-    };
+    let options = SbofOptions::new()
+        .with_header(header)
+        .with_high_precision(high_precision);
+    to_bytes_options(value, &options)
+}
 
-    value.serialize(&mut serializer)?;
+/// Converts a value that implements [`Serialize`] into a Vec<u8> of SBOF data, with specific [`SbofOptions`].
+pub fn to_bytes_options<T: Serialize + ?Sized>(
+    value: &T,
+    options: &SbofOptions,
+) -> Result<Vec<u8>> {
+    serialize_into(Vec::new(), value, options)
+}
 
-    Ok(serializer.inner)
+/// Serializes a value that implements [`Serialize`] as SBOF data directly into a [`Write`] sink. By default, there is a header, and the "High Precision Floats" feature is disabled.
+#[cfg(feature = "std")]
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    to_writer_options(writer, value, &SbofOptions::new())
 }
 
-fn generate_header(high_precision: bool) -> Serializer {
-    let mut feature_flags = 0x00;
-    if high_precision {
-        feature_flags |= 1 << 0;
-    }
-    // Version 0
-    let header = vec![0x00, feature_flags];
-    Serializer::new(header, high_precision)
+/// Serializes a value that implements [`Serialize`] as SBOF data directly into a [`Write`] sink, with specific settings.
+#[cfg(feature = "std")]
+#[deprecated(note = "use `to_writer_options` instead")]
+pub fn to_writer_settings<W: Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+    header: bool,
+    high_precision: bool,
+) -> Result<()> {
+    let options = SbofOptions::new()
+        .with_header(header)
+        .with_high_precision(high_precision);
+    to_writer_options(writer, value, &options)
 }
 
-/// No header
-fn to_bytes_with_settings<T: Serialize + ?Sized>(
-    settings: &Serializer,
+/// Serializes a value that implements [`Serialize`] as SBOF data directly into a [`Write`] sink, with specific [`SbofOptions`].
+#[cfg(feature = "std")]
+pub fn to_writer_options<W: Write, T: Serialize + ?Sized>(
+    writer: W,
     value: &T,
-) -> Result<Vec<u8>> {
-    let mut serializer = settings.dup();
+    options: &SbofOptions,
+) -> Result<()> {
+    serialize_into(writer, value, options)?;
+    Ok(())
+}
 
+/// Serializes a value that implements [`Serialize`] as SBOF data into `buf`, returning the part of `buf` that was written. By default, there is a header, and the "High Precision Floats" feature is disabled.
+///
+/// Fails with [`Error::BufferFull`] if the data doesn't fit, in which case the contents of `buf` are unspecified. Like [`serialized_size`], nothing is allocated unless the value has parts that are buffered by [`Compound`].
+pub fn to_slice<'a, T: Serialize + ?Sized>(value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8]> {
+    to_slice_options(value, buf, &SbofOptions::new())
+}
+
+/// Serializes a value that implements [`Serialize`] as SBOF data into `buf`, with specific [`SbofOptions`], returning the part of `buf` that was written.
+pub fn to_slice_options<'a, T: Serialize + ?Sized>(
+    value: &T,
+    buf: &'a mut [u8],
+    options: &SbofOptions,
+) -> Result<&'a mut [u8]> {
+    let writer = serialize_into(SliceWriter { buf, len: 0 }, value, options)?;
+    Ok(&mut writer.buf[..writer.len])
+}
+
+/// Computes how many bytes [`to_bytes_options`] would produce for a value, without building the output. The value goes through the same [`Serializer`], so the size always matches.
+///
+/// Nothing is allocated unless the value has parts that are buffered by [`Compound`], like sequences whose length isn't known up front.
+pub fn serialized_size<T: Serialize + ?Sized>(value: &T, options: &SbofOptions) -> Result<usize> {
+    Ok(serialize_into(Counter(0), value, options)?.0)
+}
+
+/// Serializes a value into `output`, starting with the header if there is one, and returns `output`.
+fn serialize_into<O: Output, T: Serialize + ?Sized>(
+    mut output: O,
+    value: &T,
+    options: &SbofOptions,
+) -> Result<O> {
+    if options.header() {
+        output.write_all(&options.to_header().to_bytes())?;
+    }
+
+    let mut serializer = Serializer::new(output, *options);
     value.serialize(&mut serializer)?;
 
+    serializer.inner.flush()?;
     Ok(serializer.inner)
 }
 
+/// A sink that the [`Serializer`] writes SBOF data to. With the `std` feature, every [`Write`] sink is one.
+pub trait Output {
+    /// Writes all of `bytes`, or fails.
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()>;
+
+    /// Called once the whole value has been written.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write + ?Sized> Output for W {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        Ok(Write::write_all(self, bytes)?)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(Write::flush(self)?)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Output for Vec<u8> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// An [`Output`] into a fixed slice, which fails with [`Error::BufferFull`] once it runs out of room.
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Output for SliceWriter<'_> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        let dest = self
+            .buf
+            .get_mut(self.len..self.len + bytes.len())
+            .ok_or(Error::BufferFull)?;
+        dest.copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+}
+
+/// An [`Output`] that only counts the bytes written to it.
+struct Counter(usize);
+
+impl Output for Counter {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.0 += bytes.len();
+        Ok(())
+    }
+}
+
 /// Implementation of [`serde::Serializer`] for SBOF.
-pub struct Serializer {
-    inner: Vec<u8>,
-    temp_bytes: Vec<u8>,
-    temp_len: usize,
-    high_precision: bool,
+pub struct Serializer<W> {
+    inner: W,
+    options: SbofOptions,
+    /// Amount of `Some`s whose value hasn't written its first byte yet. Their boolean markers are only needed if that byte is a 0 or 1.
+    pending_some: usize,
 }
 
-impl Serializer {
-    fn new(inner: Vec<u8>, high_precision: bool) -> Self {
+impl<W: Output> Serializer<W> {
+    fn new(inner: W, options: SbofOptions) -> Self {
         Serializer {
             inner,
-            temp_bytes: Vec::new(),
-            temp_len: 0,
-            high_precision,
+            options,
+            pending_some: 0,
         }
     }
 
-    fn dup(&self) -> Self {
-        Self::new(Vec::new(), self.high_precision)
+    fn dup(&self) -> Serializer<Vec<u8>> {
+        Serializer::new(Vec::new(), self.options)
+    }
+
+    /// Serializes a value on its own, with the same options.
+    fn encode<T: ?Sized + Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut serializer = self.dup();
+        value.serialize(&mut serializer)?;
+        Ok(serializer.inner)
+    }
+
+    /// Every write to the output goes through here, so that pending `Some` markers are written before the value they belong to.
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.pending_some > 0
+            && let Some(&first) = bytes.first()
+        {
+            if first == 0 || first == 1 {
+                for _ in 0..self.pending_some {
+                    self.inner.write_all(&[1])?;
+                }
+            }
+            self.pending_some = 0;
+        }
+        self.inner.write_all(bytes)?;
+        Ok(())
     }
 
     fn serialize_uint(&mut self, bytes: &[u8]) -> Result<()> {
@@ -84,9 +221,9 @@ impl Serializer {
 
         let slice = &bytes[..end];
         if slice.len() != 1 || (1..=bytes.len() as u8).contains(&slice[0]) {
-            self.inner.write_all(&[end as u8])?;
+            self.write(&[end as u8])?;
         }
-        self.inner.write_all(slice)?;
+        self.write(slice)?;
 
         Ok(())
     }
@@ -101,13 +238,54 @@ impl Serializer {
         let new = &bytes[..len];
 
         if new.len() != 1 || (1..=bytes.len() as u8).contains(&new[0]) {
-            self.inner.write_all(&[new.len() as u8])?;
+            self.write(&[new.len() as u8])?;
         }
-        self.inner.write_all(new)?;
+        self.write(new)?;
 
         Ok(())
     }
 
+    /// Writes the type tag of the next value, if the "Typed" feature flag is enabled.
+    fn tag(&mut self, tag: Tag) -> Result<()> {
+        if self.options.typed() {
+            self.write(&[tag as u8])?;
+        }
+        Ok(())
+    }
+
+    /// Writes the start of an enum: its variant index, and its name if the "Typed" feature flag is enabled.
+    fn serialize_variant(&mut self, variant_index: u32, variant: &str) -> Result<()> {
+        self.tag(Tag::Enum)?;
+        self.serialize_uint(&variant_index.to_le_bytes())?;
+        if self.options.typed() {
+            self.serialize_str_data(variant)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the start of a tuple, which is only prefixed with its length if the "Typed" feature flag is enabled.
+    fn serialize_tuple_start(&mut self, len: usize) -> Result<()> {
+        if self.options.typed() {
+            self.tag(Tag::Tuple)?;
+            self.serialize_usize(len)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a struct field, which is only prefixed with its name if the "Typed" feature flag is enabled.
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if self.options.typed() {
+            self.serialize_str_data(key)?;
+        }
+        value.serialize(self)
+    }
+
+    /// Writes a length prefixed string, without a type tag.
+    fn serialize_str_data(&mut self, v: &str) -> Result<()> {
+        self.serialize_usize(v.len())?;
+        self.write(v.as_bytes())
+    }
+
     fn serialize_usize(&mut self, val: usize) -> Result<()> {
         let bytes = val.to_le_bytes();
 
@@ -117,83 +295,95 @@ impl Serializer {
         }
 
         let slice = &bytes[..end];
-        self.inner.write_all(&[end as u8])?;
-        self.inner.write_all(slice)?;
+        self.write(&[end as u8])?;
+        self.write(slice)?;
         Ok(())
     }
 }
 
-impl ser::Serializer for &mut Serializer {
+impl<'a, W: Output> ser::Serializer for &'a mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = Self;
+    type SerializeSeq = Compound<'a, W>;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
-    type SerializeStruct = Self;
-    type SerializeStructVariant = Self;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
-        self.inner.push(if v { 1 } else { 0 });
+        self.tag(Tag::Bool)?;
+        self.write(&[if v { 1 } else { 0 }])?;
         Ok(())
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
-        self.inner.write_all(&v.to_le_bytes())?;
+        self.tag(Tag::U8)?;
+        self.write(&v.to_le_bytes())?;
         Ok(())
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
-        self.inner.write_all(&v.to_le_bytes())?;
+        self.tag(Tag::I8)?;
+        self.write(&v.to_le_bytes())?;
         Ok(())
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        self.tag(Tag::U16)?;
         let bytes = v.to_le_bytes();
         self.serialize_uint(bytes.as_slice())
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        self.tag(Tag::I16)?;
         let bytes = v.to_le_bytes();
         self.serialize_int(&bytes, v as i128)
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        self.tag(Tag::U32)?;
         let bytes = v.to_le_bytes();
         self.serialize_uint(bytes.as_slice())
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        self.tag(Tag::I32)?;
         let bytes = v.to_le_bytes();
         self.serialize_int(&bytes, v as i128)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        self.tag(Tag::U64)?;
         let bytes = v.to_le_bytes();
         self.serialize_uint(bytes.as_slice())
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        self.tag(Tag::I64)?;
         let bytes = v.to_le_bytes();
         self.serialize_int(&bytes, v as i128)
     }
 
-    fn serialize_u128(self, v: u128) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_u128(self, v: u128) -> core::result::Result<Self::Ok, Self::Error> {
+        self.tag(Tag::U128)?;
         let bytes = v.to_le_bytes();
         self.serialize_uint(bytes.as_slice())
     }
 
-    fn serialize_i128(self, v: i128) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_i128(self, v: i128) -> core::result::Result<Self::Ok, Self::Error> {
+        self.tag(Tag::I128)?;
         let bytes = v.to_le_bytes();
         self.serialize_int(bytes.as_slice(), v)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        self.tag(Tag::F32)?;
         let bits = v.to_bits();
-        if self.high_precision {
-            self.inner.write_all(&bits.to_le_bytes())?;
+        if self.options.high_precision() {
+            self.write(&bits.to_le_bytes())?;
             return Ok(());
         }
         let sign = bits & (1 << 31) != 0;
@@ -204,14 +394,15 @@ impl ser::Serializer for &mut Serializer {
             ((bits & 0x7fffff).reverse_bits() >> 9) as i32
         };
 
-        significand.serialize(&mut *self)?;
-        mantissa.serialize(&mut *self)
+        self.serialize_int(&significand.to_le_bytes(), significand as i128)?;
+        self.write(&mantissa.to_le_bytes())
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        self.tag(Tag::F64)?;
         let bits = v.to_bits();
-        if self.high_precision {
-            self.inner.write_all(&bits.to_le_bytes())?;
+        if self.options.high_precision() {
+            self.write(&bits.to_le_bytes())?;
             return Ok(());
         }
         let sign = (bits & (1 << 63)) << 63 != 0;
@@ -222,54 +413,72 @@ impl ser::Serializer for &mut Serializer {
             ((bits & 0xfffffffffffff).reverse_bits() >> 12) as i64
         };
 
-        significand.serialize(&mut *self)?;
-        mantissa.serialize(&mut *self)
+        self.serialize_int(&significand.to_le_bytes(), significand as i128)?;
+        self.serialize_int(&mantissa.to_le_bytes(), mantissa as i128)
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
-        self.serialize_u32(v as u32)
+        self.tag(Tag::Char)?;
+        self.serialize_uint(&(v as u32).to_le_bytes())
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        v.as_bytes().serialize(self)
+        self.tag(Tag::Str)?;
+        self.serialize_str_data(v)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        self.tag(Tag::Bytes)?;
         self.serialize_usize(v.len())?;
-        self.inner.write_all(v)?;
+        self.write(v)?;
         Ok(())
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        false.serialize(self)
+        if self.options.typed() {
+            self.tag(Tag::None)
+        } else {
+            self.write(&[0])
+        }
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + serde::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        if bytes.starts_with(&[0x00]) || bytes.starts_with(&[0x01]) {
-            true.serialize(&mut *self)?;
+        if self.options.typed() {
+            self.tag(Tag::Some)?;
+            return value.serialize(self);
         }
-        value.serialize(self)
+        // The marker is decided by `write` once the value's first byte is known
+        self.pending_some += 1;
+        value.serialize(&mut *self)?;
+        if self.pending_some > 0 {
+            // The value didn't write anything, so the marker is needed to tell it apart from whatever comes next
+            self.pending_some -= 1;
+            self.write(&[1])?;
+        }
+        Ok(())
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        Ok(())
+        self.tag(Tag::Unit)
     }
 
     fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok> {
-        Ok(())
+        self.tag(Tag::Unit)
     }
 
     fn serialize_unit_variant(
         self,
         _: &'static str,
         variant_index: u32,
-        _: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok> {
-        variant_index.serialize(self)
+        self.serialize_variant(variant_index, variant)?;
+        let mut frame = Compound::new_frame(self);
+        frame.tag(Tag::Unit)?;
+        frame.end()
     }
 
     fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<Self::Ok>
@@ -283,35 +492,34 @@ impl ser::Serializer for &mut Serializer {
         self,
         _: &'static str,
         variant_index: u32,
-        _: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok>
     where
         T: ?Sized + serde::Serialize,
     {
-        variant_index.serialize(&mut *self)?;
-        value.serialize(self)
+        self.serialize_variant(variant_index, variant)?;
+        let mut frame = Compound::new_frame(self);
+        frame.serialize(value)?;
+        frame.end()
     }
 
-    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.temp_bytes.clear();
-        self.temp_len = 0;
-        Ok(self)
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.tag(Tag::Seq)?;
+        Compound::new(self, len)
     }
 
-    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
-        self.temp_bytes.clear();
-        self.temp_len = 0;
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_tuple_start(len)?;
         Ok(self)
     }
 
     fn serialize_tuple_struct(
         self,
         _: &'static str,
-        _: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        self.temp_bytes.clear();
-        self.temp_len = 0;
+        self.serialize_tuple_start(len)?;
         Ok(self)
     }
 
@@ -319,42 +527,210 @@ impl ser::Serializer for &mut Serializer {
         self,
         _: &'static str,
         variant_index: u32,
-        _: &'static str,
-        _: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        variant_index.serialize(&mut *self)?;
-        self.temp_bytes.clear();
-        self.temp_len = 0;
-        Ok(self)
+        self.serialize_variant(variant_index, variant)?;
+        let mut frame = Compound::new_frame(self);
+        if frame.ser.options.typed() {
+            frame.tag(Tag::Tuple)?;
+            frame.serialize_len(len)?;
+        }
+        Ok(frame)
     }
 
-    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
-        self.temp_bytes.clear();
-        self.temp_len = 0;
-        Ok(self)
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.tag(Tag::Map)?;
+        Compound::new_map(self, len)
     }
 
-    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
-        self.temp_bytes.clear();
-        self.temp_len = 0;
-        Ok(self)
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.tag(Tag::Struct)?;
+        let mut frame = Compound::new_struct(self);
+        if frame.ser.options.typed() {
+            frame.serialize_len(len)?;
+        }
+        Ok(frame)
     }
 
     fn serialize_struct_variant(
         self,
         _: &'static str,
         variant_index: u32,
-        _: &'static str,
-        _: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        variant_index.serialize(&mut *self)?;
-        self.temp_bytes.clear();
-        self.temp_len = 0;
-        Ok(self)
+        self.serialize_variant(variant_index, variant)?;
+        let mut frame = Compound::new_struct(self);
+        if frame.ser.options.typed() {
+            frame.tag(Tag::Struct)?;
+            frame.serialize_len(len)?;
+        }
+        Ok(frame)
     }
 }
 
-impl ser::SerializeMap for &mut Serializer {
+/// Serializes sequences, maps, structs and enum variants. If the length of a sequence or map is known up front, the length prefix and elements are written straight to the output. Otherwise, the elements are buffered until the length is known.
+///
+/// In canonical mode, map entries are always buffered, so that they can be sorted by their keys. With the "Skippable" feature flag, structs and the data of enum variants are buffered, so that they can be prefixed with their length in bytes. With the "Named Fields" feature flag, struct fields are buffered, so that they can be prefixed with how many there are.
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    buffer: Option<Serializer<Vec<u8>>>,
+    entries: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+    expected_len: Option<usize>,
+    len: usize,
+    /// Whether this is a struct or enum variant, rather than a sequence or map
+    frame: bool,
+    /// Whether this is a struct with the "Named Fields" feature flag
+    named: bool,
+}
+
+impl<'a, W: Output> Compound<'a, W> {
+    fn new(ser: &'a mut Serializer<W>, len: Option<usize>) -> Result<Self> {
+        let buffer = match len {
+            Some(len) => {
+                ser.serialize_usize(len)?;
+                None
+            }
+            None => Some(ser.dup()),
+        };
+        Ok(Compound {
+            ser,
+            buffer,
+            entries: None,
+            expected_len: len,
+            len: 0,
+            frame: false,
+            named: false,
+        })
+    }
+
+    fn new_map(ser: &'a mut Serializer<W>, len: Option<usize>) -> Result<Self> {
+        if !ser.options.canonical() {
+            return Self::new(ser, len);
+        }
+        Ok(Compound {
+            ser,
+            buffer: None,
+            entries: Some(Vec::new()),
+            expected_len: None,
+            len: 0,
+            frame: false,
+            named: false,
+        })
+    }
+
+    fn new_frame(ser: &'a mut Serializer<W>) -> Self {
+        let buffer = ser.options.framed().then(|| ser.dup());
+        Compound {
+            ser,
+            buffer,
+            entries: None,
+            expected_len: None,
+            len: 0,
+            frame: true,
+            named: false,
+        }
+    }
+
+    fn new_struct(ser: &'a mut Serializer<W>) -> Self {
+        if !ser.options.named() {
+            return Self::new_frame(ser);
+        }
+        let buffer = Some(ser.dup());
+        Compound {
+            ser,
+            buffer,
+            entries: None,
+            expected_len: None,
+            len: 0,
+            frame: true,
+            named: true,
+        }
+    }
+
+    fn tag(&mut self, tag: Tag) -> Result<()> {
+        match &mut self.buffer {
+            Some(buffer) => buffer.tag(tag),
+            None => self.ser.tag(tag),
+        }
+    }
+
+    fn serialize_len(&mut self, len: usize) -> Result<()> {
+        match &mut self.buffer {
+            Some(buffer) => buffer.serialize_usize(len),
+            None => self.ser.serialize_usize(len),
+        }
+    }
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if self.named {
+            // Each field is length prefixed, so that readers can skip fields they don't know about
+            self.len += 1;
+            let value = self.ser.encode(value)?;
+            let buffer = self.buffer.as_mut().expect("named fields are buffered");
+            buffer.serialize_uint(&crate::field_id(key).to_le_bytes())?;
+            buffer.serialize_usize(value.len())?;
+            return buffer.write(&value);
+        }
+        match &mut self.buffer {
+            Some(buffer) => buffer.serialize_field(key, value),
+            None => self.ser.serialize_field(key, value),
+        }
+    }
+
+    fn serialize<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        match &mut self.buffer {
+            Some(buffer) => value.serialize(buffer),
+            None => value.serialize(&mut *self.ser),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        if self.frame {
+            let Some(buffer) = self.buffer else {
+                return Ok(());
+            };
+            let bytes = if self.named {
+                let mut fields = self.ser.dup();
+                fields.serialize_usize(self.len)?;
+                fields.write(&buffer.inner)?;
+                fields.inner
+            } else {
+                buffer.inner
+            };
+            if self.ser.options.framed() {
+                self.ser.serialize_usize(bytes.len())?;
+            }
+            return self.ser.write(&bytes);
+        }
+
+        if let Some(mut entries) = self.entries {
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            self.ser.serialize_usize(entries.len())?;
+            for (key, value) in entries {
+                self.ser.write(&key)?;
+                self.ser.write(&value)?;
+            }
+            return Ok(());
+        }
+
+        match self.buffer {
+            Some(buffer) => {
+                self.ser.serialize_usize(self.len)?;
+                self.ser.write(&buffer.inner)
+            }
+            None if self.expected_len != Some(self.len) => Err(ser::Error::custom(format!(
+                "expected {} elements, but {} were serialized",
+                self.expected_len.unwrap_or_default(),
+                self.len
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a, W: Output> ser::SerializeMap for Compound<'a, W> {
     type Ok = ();
     type Error = Error;
 
@@ -362,30 +738,34 @@ impl ser::SerializeMap for &mut Serializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, key)?;
-        self.temp_len += 1;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        self.len += 1;
+        if let Some(entries) = &mut self.entries {
+            entries.push((self.ser.encode(key)?, Vec::new()));
+            return Ok(());
+        }
+        self.serialize(key)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        if let Some(entries) = &mut self.entries {
+            let value = self.ser.encode(value)?;
+            if let Some(entry) = entries.last_mut() {
+                entry.1 = value;
+            }
+            return Ok(());
+        }
+        self.serialize(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        let len = self.temp_len;
-        self.serialize_usize(len)?;
-        self.inner.write_all(&self.temp_bytes)?;
-        Ok(())
+        Compound::end(self)
     }
 }
 
-impl ser::SerializeSeq for &mut Serializer {
+impl<'a, W: Output> ser::SerializeSeq for Compound<'a, W> {
     type Ok = ();
     type Error = Error;
 
@@ -393,59 +773,48 @@ impl ser::SerializeSeq for &mut Serializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_len += 1;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        self.len += 1;
+        self.serialize(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        let len = self.temp_len;
-        self.serialize_usize(len)?;
-        self.inner.write_all(&self.temp_bytes)?;
-        Ok(())
+        Compound::end(self)
     }
 }
 
-impl ser::SerializeStruct for &mut Serializer {
+impl<'a, W: Output> ser::SerializeStruct for Compound<'a, W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        Compound::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.inner.write_all(&self.temp_bytes)?;
-        Ok(())
+        Compound::end(self)
     }
 }
 
-impl ser::SerializeStructVariant for &mut Serializer {
+impl<'a, W: Output> ser::SerializeStructVariant for Compound<'a, W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        Compound::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.inner.write_all(&self.temp_bytes)?;
-        Ok(())
+        Compound::end(self)
     }
 }
 
-impl ser::SerializeTuple for &mut Serializer {
+impl<W: Output> ser::SerializeTuple for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -453,18 +822,15 @@ impl ser::SerializeTuple for &mut Serializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.inner.write_all(&self.temp_bytes)?;
         Ok(())
     }
 }
 
-impl ser::SerializeTupleStruct for &mut Serializer {
+impl<W: Output> ser::SerializeTupleStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -472,18 +838,15 @@ impl ser::SerializeTupleStruct for &mut Serializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.inner.write_all(&self.temp_bytes)?;
         Ok(())
     }
 }
 
-impl ser::SerializeTupleVariant for &mut Serializer {
+impl<'a, W: Output> ser::SerializeTupleVariant for Compound<'a, W> {
     type Ok = ();
     type Error = Error;
 
@@ -491,20 +854,17 @@ impl ser::SerializeTupleVariant for &mut Serializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        let bytes = to_bytes_with_settings(self, value)?;
-        self.temp_bytes.write_all(&bytes)?;
-        Ok(())
+        self.serialize(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.inner.write_all(&self.temp_bytes)?;
-        Ok(())
+        Compound::end(self)
     }
 }
 
 #[test]
 fn usize_test() -> Result<()> {
-    let mut serializer = Serializer::new(Vec::new(), false);
+    let mut serializer = Serializer::new(Vec::new(), SbofOptions::new());
     serializer.serialize_usize(256)?;
     assert_eq!(serializer.inner, [2, 0, 1]);
     Ok(())
@@ -563,9 +923,147 @@ fn float_test() -> Result<()> {
     assert_eq!(to_bytes_testing(&2.0f32)?, [0x00, 0x01]);
     assert_eq!(to_bytes_testing(&52.0f32)?, [0x05, 0x05]);
     assert_eq!(
-        to_bytes_settings(&3.563f32, false, true)?,
+        to_bytes_options(
+            &3.563f32,
+            &SbofOptions::new()
+                .with_header(false)
+                .with_high_precision(true)
+        )?,
         [0x31, 0x08, 0x64, 0x40]
     );
 
     Ok(())
 }
+
+#[test]
+#[cfg(feature = "std")]
+fn writer_test() -> Result<()> {
+    let mut writer = std::io::Cursor::new(Vec::new());
+    to_writer(&mut writer, &(5u16, "hi", [256u32]))?;
+    assert_eq!(
+        writer.into_inner(),
+        [0x00, 0x00, 0x05, 0x01, 0x02, b'h', b'i', 0x02, 0x00, 0x01]
+    );
+    Ok(())
+}
+
+#[test]
+fn option_test() -> Result<()> {
+    assert_eq!(to_bytes_testing(&Some(5u8))?, [0x05]);
+    assert_eq!(to_bytes_testing(&Some(1u8))?, [0x01, 0x01]);
+    assert_eq!(to_bytes_testing(&Some(Some(0u8)))?, [0x01, 0x01, 0x00]);
+    assert_eq!(to_bytes_testing(&Some(None::<u8>))?, [0x01, 0x00]);
+    assert_eq!(to_bytes_testing(&Some(()))?, [0x01]);
+    assert_eq!(to_bytes_testing(&Some(Some(())))?, [0x01, 0x01]);
+    assert_eq!(to_bytes_testing(&(Some(()), 5u8))?, [0x01, 0x05]);
+
+    // Values that write nothing keep their marker, so that they still round trip
+    let options = SbofOptions::new().with_header(false).with_canonical(true);
+    let pair = (Some(()), 1u8);
+    let bytes = to_bytes_options(&pair, &options)?;
+    assert_eq!(
+        crate::from_bytes_options::<(Option<()>, u8)>(&bytes, &options)?,
+        pair
+    );
+    let nested = Some((Some(()), 0u8));
+    let bytes = to_bytes_options(&nested, &options)?;
+    assert_eq!(
+        crate::from_bytes_options::<Option<(Option<()>, u8)>>(&bytes, &options)?,
+        nested
+    );
+    let units = (Some(Some(())), None::<()>, Some(()));
+    let bytes = to_bytes_options(&units, &options)?;
+    assert_eq!(
+        crate::from_bytes_options::<(Option<Option<()>>, Option<()>, Option<()>)>(
+            &bytes, &options
+        )?,
+        units
+    );
+    Ok(())
+}
+
+#[test]
+fn unknown_length_test() -> Result<()> {
+    struct Odd(Vec<u8>);
+
+    impl Serialize for Odd {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().filter(|v| *v % 2 == 1))
+        }
+    }
+
+    assert_eq!(
+        to_bytes_testing(&Some(Odd(vec![1, 2, 3, 4])))?,
+        [0x01, 0x01, 0x02, 0x01, 0x03]
+    );
+    assert_eq!(to_bytes_testing(&Odd(vec![2]))?, [0x01, 0x00]);
+    Ok(())
+}
+
+#[test]
+fn canonical_test() -> Result<()> {
+    let map = std::collections::HashMap::from([(300u16, 'a'), (2, 'b'), (7, 'c'), (0, 'd')]);
+    let options = SbofOptions::new().with_header(false).with_canonical(true);
+    assert_eq!(
+        to_bytes_options(&map, &options)?,
+        [
+            0x01, 0x04, 0x00, b'd', 0x01, 0x02, b'b', 0x02, 0x2c, 0x01, b'a', 0x07, b'c'
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn serialized_size_test() -> Result<()> {
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f32),
+        Rect { w: u16, h: u16 },
+    }
+
+    let value = (
+        Some(Some(0u8)),
+        "hi",
+        vec![
+            Shape::Point,
+            Shape::Circle(0.1),
+            Shape::Rect { w: 300, h: 1 },
+        ],
+        std::collections::HashMap::from([(1u64, -2.5f64), (1 << 40, f64::NAN)]),
+        Some(1i128 << 100),
+    );
+    for options in [
+        SbofOptions::new(),
+        SbofOptions::new().with_header(false),
+        SbofOptions::new().with_high_precision(true),
+        SbofOptions::new().with_canonical(true),
+        SbofOptions::new().with_skippable(true),
+        SbofOptions::new().with_typed(true),
+        SbofOptions::new().with_named_fields(true),
+    ] {
+        assert_eq!(
+            serialized_size(&value, &options)?,
+            to_bytes_options(&value, &options)?.len()
+        );
+    }
+    assert_eq!(serialized_size(&5u16, &SbofOptions::new())?, 3);
+    Ok(())
+}
+
+#[test]
+fn slice_test() -> Result<()> {
+    let value = (5u16, "hi", [256u32]);
+    let mut buf = [0; 16];
+    assert_eq!(
+        to_slice(&value, &mut buf)?,
+        [0x00, 0x00, 0x05, 0x01, 0x02, b'h', b'i', 0x02, 0x00, 0x01]
+    );
+
+    let options = SbofOptions::new().with_header(false);
+    assert_eq!(to_slice_options(&value, &mut buf[..8], &options)?.len(), 8);
+    let err = to_slice_options(&value, &mut buf[..7], &options).unwrap_err();
+    assert!(matches!(err, Error::BufferFull));
+    assert!(matches!(to_slice(&value, &mut []), Err(Error::BufferFull)));
+    Ok(())
+}
@@ -0,0 +1,71 @@
+/// The type tags that every value starts with when the "Typed" feature flag is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Tag {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Char,
+    Str,
+    Bytes,
+    None,
+    Some,
+    Seq,
+    Tuple,
+    Map,
+    Struct,
+    Enum,
+}
+
+impl Tag {
+    const ALL: [Tag; 24] = [
+        Tag::Unit,
+        Tag::Bool,
+        Tag::U8,
+        Tag::U16,
+        Tag::U32,
+        Tag::U64,
+        Tag::U128,
+        Tag::I8,
+        Tag::I16,
+        Tag::I32,
+        Tag::I64,
+        Tag::I128,
+        Tag::F32,
+        Tag::F64,
+        Tag::Char,
+        Tag::Str,
+        Tag::Bytes,
+        Tag::None,
+        Tag::Some,
+        Tag::Seq,
+        Tag::Tuple,
+        Tag::Map,
+        Tag::Struct,
+        Tag::Enum,
+    ];
+
+    pub(crate) fn from_u8(byte: u8) -> Option<Tag> {
+        Self::ALL.get(byte as usize).copied()
+    }
+}
+
+#[test]
+fn tag_test() {
+    for (byte, tag) in Tag::ALL.iter().enumerate() {
+        assert_eq!(*tag as u8, byte as u8);
+        assert_eq!(Tag::from_u8(byte as u8), Some(*tag));
+    }
+    assert_eq!(Tag::from_u8(Tag::ALL.len() as u8), None);
+}
@@ -0,0 +1,354 @@
+//! A dynamically typed SBOF value.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use serde::ser::{
+    Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleVariant,
+};
+
+use crate::{
+    Error, Header, Result, SbofOptions,
+    buf::Buf,
+    de::Deserializer,
+    schema::{Schema, VariantKind},
+};
+
+pub(crate) const TYPED_UNSUPPORTED: Error = Error::Unsupported {
+    name: "Value",
+    reason: "values follow a schema, so they don't support the \"Typed\" feature flag",
+};
+
+pub(crate) const NAMED_UNSUPPORTED: Error = Error::Unsupported {
+    name: "Value",
+    reason: "values follow a schema, so they don't support the \"Named Fields\" feature flag",
+};
+
+/// Any value that can be stored in SBOF. Values are decoded with a [`Schema`], since SBOF data doesn't describe itself, but each value keeps enough information to be encoded again without one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    Unit,
+    Option(Option<Box<Value>>),
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    /// The fields of a struct, in order
+    Struct(Vec<(String, Value)>),
+    /// A variant of an enum. The value is [`Value::Unit`] for unit variants, [`Value::Tuple`] for tuple variants, and [`Value::Struct`] for struct variants.
+    Enum {
+        index: u32,
+        variant: String,
+        form: VariantForm,
+        value: Box<Value>,
+    },
+}
+
+/// How the data of a [`Value::Enum`] is laid out. A newtype variant can hold a tuple or struct, so this can't be told from the value alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantForm {
+    Unit,
+    Newtype,
+    Tuple,
+    Struct,
+}
+
+impl From<&VariantKind> for VariantForm {
+    fn from(kind: &VariantKind) -> Self {
+        match kind {
+            VariantKind::Unit => VariantForm::Unit,
+            VariantKind::Newtype(_) => VariantForm::Newtype,
+            VariantKind::Tuple(_) => VariantForm::Tuple,
+            VariantKind::Struct(_) => VariantForm::Struct,
+        }
+    }
+}
+
+impl Value {
+    /// Decodes SBOF data, including its header, into a value with the layout of `schema`.
+    pub fn from_bytes(bytes: &[u8], schema: &Schema) -> Result<Value> {
+        Self::from_bytes_options(bytes, schema, &SbofOptions::new())
+    }
+
+    /// Decodes SBOF data into a value with the layout of `schema`, with specific [`SbofOptions`]. Data with the "Typed" or "Named Fields" feature flags isn't supported.
+    pub fn from_bytes_options(
+        bytes: &[u8],
+        schema: &Schema,
+        options: &SbofOptions,
+    ) -> Result<Value> {
+        let mut bytes = bytes;
+        let options = if options.header() {
+            options.apply_header(Header::take(&mut bytes)?)
+        } else {
+            *options
+        };
+        if options.typed() {
+            return Err(TYPED_UNSUPPORTED);
+        }
+        if options.named_fields() {
+            return Err(NAMED_UNSUPPORTED);
+        }
+        let mut deserializer = Deserializer::new(Buf::new(bytes), options);
+        let value = deserializer.deserialize_value_root(schema)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+
+    /// Encodes the value as SBOF data, with a header.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_options(&SbofOptions::new())
+    }
+
+    /// Encodes the value as SBOF data, with specific [`SbofOptions`]. The "Typed" and "Named Fields" feature flags aren't supported.
+    pub fn to_bytes_options(&self, options: &SbofOptions) -> Result<Vec<u8>> {
+        if options.typed() {
+            return Err(TYPED_UNSUPPORTED);
+        }
+        if options.named_fields() {
+            return Err(NAMED_UNSUPPORTED);
+        }
+        crate::to_bytes_options(&Encode(self), options)
+    }
+}
+
+/// Serializes a [`Value`] the same way as the Rust type it describes. Names aren't `'static`, so they're left empty, which is fine for SBOF without the "Typed" feature flag, but makes this useless for other serializers.
+struct Encode<'a>(&'a Value);
+
+impl Serialize for Encode<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::U8(v) => serializer.serialize_u8(*v),
+            Value::U16(v) => serializer.serialize_u16(*v),
+            Value::U32(v) => serializer.serialize_u32(*v),
+            Value::U64(v) => serializer.serialize_u64(*v),
+            Value::U128(v) => serializer.serialize_u128(*v),
+            Value::I8(v) => serializer.serialize_i8(*v),
+            Value::I16(v) => serializer.serialize_i16(*v),
+            Value::I32(v) => serializer.serialize_i32(*v),
+            Value::I64(v) => serializer.serialize_i64(*v),
+            Value::I128(v) => serializer.serialize_i128(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::Char(v) => serializer.serialize_char(*v),
+            Value::Str(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::Unit => serializer.serialize_unit(),
+            Value::Option(None) => serializer.serialize_none(),
+            Value::Option(Some(v)) => serializer.serialize_some(&Encode(v)),
+            Value::Seq(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(&Encode(value))?;
+                }
+                seq.end()
+            }
+            Value::Tuple(values) => {
+                let mut tuple = serializer.serialize_tuple(values.len())?;
+                for value in values {
+                    tuple.serialize_element(&Encode(value))?;
+                }
+                tuple.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(&Encode(key), &Encode(value))?;
+                }
+                map.end()
+            }
+            Value::Struct(fields) => {
+                let mut s = serializer.serialize_struct("", fields.len())?;
+                for (_, value) in fields {
+                    s.serialize_field("", &Encode(value))?;
+                }
+                s.end()
+            }
+            Value::Enum {
+                index, form, value, ..
+            } => match (form, &**value) {
+                (VariantForm::Unit, Value::Unit) => {
+                    serializer.serialize_unit_variant("", *index, "")
+                }
+                (VariantForm::Newtype, value) => {
+                    serializer.serialize_newtype_variant("", *index, "", &Encode(value))
+                }
+                (VariantForm::Tuple, Value::Tuple(values)) => {
+                    let mut tuple =
+                        serializer.serialize_tuple_variant("", *index, "", values.len())?;
+                    for value in values {
+                        tuple.serialize_field(&Encode(value))?;
+                    }
+                    tuple.end()
+                }
+                (VariantForm::Struct, Value::Struct(fields)) => {
+                    let mut s =
+                        serializer.serialize_struct_variant("", *index, "", fields.len())?;
+                    for (_, value) in fields {
+                        s.serialize_field("", &Encode(value))?;
+                    }
+                    s.end()
+                }
+                _ => Err(serde::ser::Error::custom(
+                    "the value of an enum doesn't match the form of its variant",
+                )),
+            },
+        }
+    }
+}
+
+#[test]
+fn round_trip_test() -> Result<()> {
+    use crate::schema::{Field, Variant, VariantKind};
+
+    #[derive(serde::Serialize)]
+    struct Size {
+        w: u16,
+        h: u16,
+    }
+
+    #[derive(serde::Serialize)]
+    enum Shape {
+        Point,
+        Circle(f32),
+        Rect { w: u16, h: u16 },
+        Square(Size),
+    }
+
+    #[derive(serde::Serialize)]
+    struct Drawing {
+        name: String,
+        shapes: Vec<Shape>,
+        tags: std::collections::BTreeMap<u8, Option<bool>>,
+        origin: (i64, i64),
+        thumbnail: Option<Option<char>>,
+    }
+
+    let shape = Schema::Enum {
+        name: "Shape".into(),
+        variants: vec![
+            Variant::new("Point", VariantKind::Unit),
+            Variant::new("Circle", VariantKind::Newtype(Schema::F32)),
+            Variant::new(
+                "Rect",
+                VariantKind::Struct(vec![
+                    Field::new("w", Schema::U16),
+                    Field::new("h", Schema::U16),
+                ]),
+            ),
+            Variant::new(
+                "Square",
+                VariantKind::Newtype(Schema::Struct {
+                    name: "Size".into(),
+                    fields: vec![Field::new("w", Schema::U16), Field::new("h", Schema::U16)],
+                }),
+            ),
+        ],
+    };
+    let schema = Schema::Struct {
+        name: "Drawing".into(),
+        fields: vec![
+            Field::new("name", Schema::Str),
+            Field::new("shapes", Schema::Seq(Box::new(shape))),
+            Field::new(
+                "tags",
+                Schema::Map {
+                    key: Box::new(Schema::U8),
+                    value: Box::new(Schema::Option(Box::new(Schema::Bool))),
+                },
+            ),
+            Field::new("origin", Schema::Tuple(vec![Schema::I64, Schema::I64])),
+            Field::new(
+                "thumbnail",
+                Schema::Option(Box::new(Schema::Option(Box::new(Schema::Char)))),
+            ),
+        ],
+    };
+
+    let drawing = Drawing {
+        name: "house".into(),
+        shapes: vec![
+            Shape::Point,
+            Shape::Circle(0.5),
+            Shape::Rect { w: 300, h: 1 },
+            Shape::Square(Size { w: 2, h: 2 }),
+        ],
+        tags: [(1, Some(true)), (2, None)].into(),
+        origin: (-1, 1),
+        thumbnail: Some(None),
+    };
+    let bytes = crate::to_bytes_options(&drawing, &SbofOptions::new().with_high_precision(true))?;
+
+    let value = Value::from_bytes(&bytes, &schema)?;
+    let Value::Struct(fields) = &value else {
+        panic!("expected struct, found {value:?}");
+    };
+    assert_eq!(fields[0], ("name".into(), Value::Str("house".into())));
+    assert_eq!(
+        fields[1].1,
+        Value::Seq(vec![
+            Value::Enum {
+                index: 0,
+                variant: "Point".into(),
+                form: VariantForm::Unit,
+                value: Box::new(Value::Unit),
+            },
+            Value::Enum {
+                index: 1,
+                variant: "Circle".into(),
+                form: VariantForm::Newtype,
+                value: Box::new(Value::F32(0.5)),
+            },
+            Value::Enum {
+                index: 2,
+                variant: "Rect".into(),
+                form: VariantForm::Struct,
+                value: Box::new(Value::Struct(vec![
+                    ("w".into(), Value::U16(300)),
+                    ("h".into(), Value::U16(1)),
+                ])),
+            },
+            Value::Enum {
+                index: 3,
+                variant: "Square".into(),
+                form: VariantForm::Newtype,
+                value: Box::new(Value::Struct(vec![
+                    ("w".into(), Value::U16(2)),
+                    ("h".into(), Value::U16(2)),
+                ])),
+            },
+        ])
+    );
+    assert_eq!(
+        fields[4].1,
+        Value::Option(Some(Box::new(Value::Option(None))))
+    );
+
+    let options = SbofOptions::new().with_high_precision(true);
+    assert_eq!(value.to_bytes_options(&options)?, bytes);
+
+    let options = options.with_skippable(true);
+    let bytes = crate::to_bytes_options(&drawing, &options)?;
+    let value = Value::from_bytes(&bytes, &schema)?;
+    assert_eq!(value.to_bytes_options(&options)?, bytes);
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use sbof::{Result, from_bytes, idl::Idl, schema::Schema, to_bytes};
+
+/// The code generated for `codegen/keywords.sbof`, which has to compile
+#[allow(non_camel_case_types)]
+mod keywords {
+    include!("codegen/keywords.rs");
+}
+
+use keywords::{Self_, Super};
+
+#[test]
+fn keywords() -> Result<()> {
+    let idl = Idl::parse(include_str!("codegen/keywords.sbof"))?;
+    assert_eq!(idl.to_rust()?, include_str!("codegen/keywords.rs"));
+
+    // The renamed names are still the original ones to serde
+    assert_eq!(idl.schema("Self"), Some(Schema::of::<Self_>()?));
+    assert_eq!(idl.schema("Super"), Some(Schema::of::<Super>()?));
+
+    for value in [
+        Super::Self_,
+        Super::crate_(1),
+        Super::super_ {
+            self_: true,
+            r#match: -1,
+        },
+    ] {
+        let value = Self_ {
+            crate_: 2,
+            self_: "self".into(),
+            super_: Some(value),
+            r#type: [3, 4],
+        };
+        assert_eq!(from_bytes::<Self_>(&to_bytes(&value)?)?, value);
+    }
+
+    Ok(())
+}
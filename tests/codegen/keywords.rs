@@ -0,0 +1,27 @@
+// Generated from an SBOF schema. Don't edit this file directly.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Self")]
+pub struct Self_ {
+    #[serde(rename = "crate")]
+    pub crate_: u8,
+    #[serde(rename = "self")]
+    pub self_: String,
+    #[serde(rename = "super")]
+    pub super_: Option<Super>,
+    pub r#type: [u16; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Super {
+    #[serde(rename = "Self")]
+    Self_,
+    #[serde(rename = "crate")]
+    crate_(u8),
+    #[serde(rename = "super")]
+    super_ {
+        #[serde(rename = "self")]
+        self_: bool,
+        r#match: i32,
+    },
+}
@@ -1,3 +1,6 @@
+// Failing cases are saved to files, and mismatches are reported as `Error::Io`, which needs the `std` feature
+#![cfg(feature = "std")]
+
 use std::{
     collections::HashMap,
     fs,
@@ -148,6 +151,37 @@ fn all() -> Result<()> {
         Ok(Ok(deser)) => deser,
     };
 
+    // Everything the serializer writes in canonical mode must be accepted by the canonical deserializer
+    let canonical = SbofOptions::new().with_canonical(true);
+    let canonical_bytes = to_bytes_options(&test_struct, &canonical)?;
+    if let Err(e) = from_bytes_options::<TestStruct>(&canonical_bytes, &canonical) {
+        failed_case(&test_struct, &canonical_bytes)?;
+        Err(e)?
+    }
+
+    // Every feature flag that changes the encoding must deserialize to the same value
+    for options in [
+        SbofOptions::new().with_typed(true),
+        SbofOptions::new().with_skippable(true),
+        SbofOptions::new().with_named_fields(true),
+    ] {
+        let flag_bytes = to_bytes_options(&test_struct, &options)?;
+        match from_bytes_options::<TestStruct>(&flag_bytes, &options) {
+            Err(e) => {
+                failed_case(&test_struct, &flag_bytes)?;
+                Err(e)?
+            }
+            Ok(flag_deser) if flag_deser != test_struct => {
+                failed_case_eq(&test_struct, &flag_deser, &flag_bytes)?;
+                return Err(Error::Io(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "failed",
+                )));
+            }
+            Ok(_) => {}
+        }
+    }
+
     if test_struct != deser {
         failed_case_eq(&test_struct, &deser, &bytes)?;
         return Err(Error::Io(std::io::Error::new(
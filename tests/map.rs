@@ -1,3 +1,6 @@
+// A map that doesn't survive the round trip is reported as `Error::Io`, which needs the `std` feature
+#![cfg(feature = "std")]
+
 use std::{
     collections::HashMap,
     fs,